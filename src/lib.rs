@@ -4,11 +4,17 @@ mod plotter;
 mod svg;
 
 pub use svg::SvgComposer;
-pub use parser::GerberReader;
+pub use parser::{ GerberReader, SourceSpan };
 pub use parser::FormatSpecification;
 pub use plotter::Plotter;
-pub use plotter::Region;
+pub use plotter::{ PlotterState, RawPath, RawPathElement, PathType, CircularDirection };
+pub use plotter::{ Region, RegionContour, RegionIssue, RegionIssueKind, Tree, TreeWalk };
+pub use plotter::{ Path, PathElement, StrokePathElement, AlgebraicPathElement, Line, Arc };
+pub use plotter::{ boolean, distance, element_distance, flatten, offset, BooleanOp, Clearance, FillRule, FlattenSide, Tolerance, Transform };
 pub use plotter::IntersectorEnum;
+pub use plotter::{ Bvh, ElementRef, LayerIndex };
+pub use plotter::{ convex_hull, min_area_rectangle, OrientedRectangle };
+pub use plotter::CubicBezier;
 
 #[cfg(test)]
 mod test {
@@ -39,6 +45,7 @@ mod integration_test {
   use crate::parser;
   use crate::plotter;
   use crate::svg::SvgComposer;
+  use std::f32::consts::PI;
 
 
   fn join<T,S, E>(r1: Result<T, E>, r2: Result<S, E>) -> Result<(T,S), E> {
@@ -54,21 +61,28 @@ mod integration_test {
   static ROOT_DIR: &str = "./test-visual";
   static GERBER_FOLDER: &str = "gerber";
   static SVG_FOLDER: &str = "expected/";
-  fn files_list() -> Vec<&'static str> { 
+  // Painted area and bounding box [min x, min y, max x, max y] of each case,
+  // as the images in `test-visual/expected` have them.
+  fn files_list() -> Vec<(&'static str, f32, [f32; 4])> { 
     vec! (
-      /*
-    */
-    "smokes/one-1",
+    ("smokes/one-1", 0.1 + PI * 0.01, [-0.6, -0.1, 0.1, 0.1]),
+    ("strokes/rect-tool-multi-segment", 0.464_58, [-0.1, -0.05, 1.1, 0.85]),
+    ("strokes/rect-tool-single-segment", 0.13, [-0.1, -0.05, 0.6, 0.35]),
+    ("strokes/circle-tool-zero-length", PI * 0.0625, [-0.25, -0.25, 0.25, 0.25]),
+    ("strokes/rect-tool-zero-length", 0.125, [-0.25, -0.125, 0.25, 0.125]),
+    ("arc-strokes/zero-length", PI * 0.015_625, [-0.125, -0.125, 0.125, 0.125]),
+    // two annuli of 0.15 width around circles of 0.5 radius
+    ("arc-strokes/full-circle", 2.0 * PI * 0.15, [-1.075, -0.575, 1.375, 0.575]),
+    ("regions/region-with-arc-cut-in", 0.8 - PI * 0.09, [0.2, 0.2, 1.2, 1.0]),
+    ("regions/region-with-arcs", 2.25 - (4.0 - PI) * 0.25, [0.0, 0.0, 1.5, 1.5]),
+    ("regions/region-with-cut-in-line", 1.35 - 0.16 - 0.12, [12.2, 25.7, 13.1, 27.2]),
+    ("regions/region-with-lines", 0.2 + 0.16, [0.2, 0.1, 1.1, 0.9]),
     /*
     "smokes/one",
     "smokes/two",
     "smokes/three",
     "strokes/circle-tool-multi-segment",
     "strokes/circle-tool-single-segment",
-    "strokes/circle-tool-zero-length",
-    "strokes/rect-tool-multi-segment",
-    "strokes/rect-tool-single-segment",
-    "strokes/rect-tool-zero-length",
     "arc-strokes/single-quadrant-I-to-II",
     "arc-strokes/single-quadrant-II-to-III",
     "arc-strokes/single-quadrant-III-to-IV",
@@ -89,28 +103,33 @@ mod integration_test {
     "arc-strokes/multi-quadrant-IV-to-II",
     "arc-strokes/multi-quadrant-IV-to-III",
     "arc-strokes/multi-quadrant-IV-to-I",
-    "arc-strokes/zero-length",
-    "arc-strokes/full-circle",
 
       "real-world/simple",
       "real-world/hard_one",
-    "regions/region-with-arc-cut-in",
-    "regions/region-with-arcs",
-    "regions/region-with-cut-in-line",
-    "regions/region-with-lines",
     "regions/shitty-region-with-lines",
     */
     
     )
   }
 
+  fn bounding_box(regions: &[plotter::Region]) -> [f32; 4] {
+    let exact = plotter::Tolerance::new(0.0);
+    regions.iter()
+      .flat_map(|region| region.contours())
+      .flat_map(|path| path.elements.iter())
+      .map(|element| plotter::bounds(element.as_ref(), &exact))
+      .fold([f32::INFINITY, f32::INFINITY, -f32::INFINITY, -f32::INFINITY], |b, (min, max)| {
+        [b[0].min(min.x), b[1].min(min.y), b[2].max(max.x), b[3].max(max.y)]
+      })
+  }
+
 
   #[test]
   fn run_through() {
     let root = Path::new(ROOT_DIR);
     let grb_path = root.join(GERBER_FOLDER);
 
-    for file in files_list().into_iter().map(String::from) {
+    for (file, area, expected_box) in files_list().into_iter() {
       let grb = grb_path.join(format!("{}.gbr", file));
       println!("processing {}", grb.to_str().unwrap());
       match File::open(&grb) {
@@ -121,7 +140,7 @@ mod integration_test {
             reader.read_to_string(&mut content).unwrap(); 
             content
           };
-          let (result, unit) = {
+          let (result, unit, tolerance) = {
             let mut parser = parser::GerberReader::new(&file_content);
             let mut plotter = plotter::Plotter::new();
             {
@@ -137,9 +156,17 @@ mod integration_test {
               }
             }
             let u = plotter.get_units();
-            (plotter.get_result(), u)
+            let tolerance = plotter.get_tolerance();
+            (plotter.get_result(), u, tolerance)
           };
 
+          let painted: f32 = plotter::flatten(&result, &tolerance).iter().map(|region| region.area()).sum();
+          assert!((painted - area).abs() < 1e-3, "{}: area {} instead of {}", file, painted, area);
+          let result_box = bounding_box(&result);
+          for (side, expected) in result_box.iter().zip(expected_box.iter()) {
+            assert!((side - expected).abs() < 1e-3, "{}: bounding box {:?} instead of {:?}", file, result_box, expected_box);
+          }
+
 
           let composer = SvgComposer::new(result, unit);
           let result = composer.compose();
@@ -150,11 +177,8 @@ mod integration_test {
           }
           File::create(file_name_to_save).map(move |mut f| f.write_all(result.as_bytes())).unwrap().unwrap();
         },
-        Err(e) => {
-          println!("Error: {:?} ", e);
-        }
+        Err(e) => panic!("{}: {:?}", file, e)
       }
     }
-    assert_eq!(true, true);
   }
 }
@@ -1,7 +1,7 @@
 mod commands;
 mod reader;
 
-pub use reader::GerberReader;
+pub use reader::{ GerberReader, SourceSpan, WithSpans };
 pub use commands::{ 
   Polarity,
   OperationType, 
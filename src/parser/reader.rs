@@ -4,17 +4,62 @@ use nom::{
   branch::{ alt },
 };
 
+/// Place of the command block in the source: byte range and line number (1-based)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceSpan {
+  pub start: usize,
+  pub end: usize,
+  pub line: usize
+}
+
+impl SourceSpan {
+  pub fn merge(&self, other: &SourceSpan) -> SourceSpan {
+    let first = if self.start <= other.start { self } else { other };
+    SourceSpan {
+      start: first.start,
+      end: self.end.max(other.end),
+      line: first.line
+    }
+  }
+}
 
 pub struct GerberReader<'a> {
-  pointer: &'a str
+  input: &'a str,
+  pointer: &'a str,
+  line: usize,
+  span: Option<SourceSpan>
 }
 
 impl<'a> GerberReader<'a> {
   pub fn new(input: &'a str) -> Self {
     GerberReader {
-      pointer: input
+      input,
+      pointer: input,
+      line: 1,
+      span: None
     }
   }
+
+  /// Span of the command block returned by the last `next` call.
+  pub fn last_span(&self) -> Option<&SourceSpan> {
+    self.span.as_ref()
+  }
+
+  pub fn with_spans(self) -> WithSpans<'a> {
+    WithSpans { reader: self }
+  }
+
+  fn advance(&mut self, rest: &'a str) {
+    let start = self.input.len() - self.pointer.len();
+    let consumed = &self.pointer[..self.pointer.len() - rest.len()];
+    self.span.replace(SourceSpan {
+      start,
+      end: start + consumed.trim_end().len(),
+      line: self.line
+    });
+    self.line += consumed.matches('\n').count();
+    self.pointer = rest;
+  }
 }
 
 
@@ -29,7 +74,7 @@ impl<'a> Iterator for GerberReader<'a> {
     } else {
       match alt((simple_command_block, extended_command))(self.pointer) {
         Ok((rest, command)) => {
-          self.pointer = rest;
+          self.advance(rest);
           Some(Ok(command))
         },
         Err(Error(err)) => {
@@ -48,6 +93,22 @@ impl<'a> Iterator for GerberReader<'a> {
   }
 }
 
+pub struct WithSpans<'a> {
+  reader: GerberReader<'a>
+}
+
+impl<'a> Iterator for WithSpans<'a> {
+  type Item = (ParseResult<&'a str>, Option<SourceSpan>);
+  fn next(&mut self) -> Option<Self::Item> {
+    let result = self.reader.next()?;
+    let span = match result {
+      Ok(_) => self.reader.span.clone(),
+      Err(_) => None
+    };
+    Some((result, span))
+  }
+}
+
 #[test]
 fn read_several_aperture_extended_commands() {
   let cmds = r"%AMOC8*
@@ -100,3 +161,14 @@ fn read_operation() {
     panic!("wrong");
   }
 }
+
+#[test]
+fn spans_of_commands() {
+  let cmds = "%MOIN*%\nG01*\nX02Y01D01*\n";
+  let spans = GerberReader::new(cmds).with_spans()
+    .map(|(_, span)| span.unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(spans[1], SourceSpan { start: 8, end: 12, line: 2 });
+  assert_eq!(spans[2], SourceSpan { start: 13, end: 23, line: 3 });
+  assert_eq!(&cmds[spans[2].start..spans[2].end], "X02Y01D01*");
+}
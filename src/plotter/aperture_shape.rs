@@ -0,0 +1,312 @@
+extern crate nalgebra as na;
+use std::f32::consts::PI;
+use na::Vector2;
+use super::{ Arc, Line, CircularDirection, StrokePathElement };
+
+type Vec2 = Vector2<f32>;
+
+const ANGLE_EPSILON: f32 = 1e-5;
+
+fn angle_of(v: &Vec2) -> f32 {
+  v.y.atan2(v.x)
+}
+
+fn direction_of(angle: f32) -> Vec2 {
+  let (y, x) = angle.sin_cos();
+  Vec2::new(x, y)
+}
+
+// how far we should rotate clockwise from `from` to reach `to`
+fn cw_distance(from: f32, to: f32) -> f32 {
+  let d = (from - to).rem_euclid(2.0 * PI);
+  if d > 2.0 * PI - ANGLE_EPSILON { 0.0 } else { d }
+}
+
+/// Convex aperture outline: convex polygon (counter-clockwise) inflated by `radius`.
+///
+/// Circle is a single vertex with non-zero radius, obround is a segment with radius,
+/// rect and polygon apertures have zero radius.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApertureShape {
+  vertices: Vec<Vec2>,
+  radius: f32
+}
+
+impl ApertureShape {
+  pub fn circle(radius: f32) -> Self {
+    ApertureShape {
+      vertices: vec!(Vec2::new(0.0, 0.0)),
+      radius
+    }
+  }
+
+  pub fn rect(width: f32, height: f32) -> Self {
+    let hw = width.abs() / 2.0;
+    let hh = height.abs() / 2.0;
+    ApertureShape {
+      vertices: vec!(
+        Vec2::new(-hw, -hh),
+        Vec2::new(hw, -hh),
+        Vec2::new(hw, hh),
+        Vec2::new(-hw, hh)
+      ),
+      radius: 0.0
+    }
+  }
+
+  pub fn obround(width: f32, height: f32) -> Self {
+    let (width, height) = (width.abs(), height.abs());
+    if (width - height).abs() <= f32::EPSILON {
+      Self::circle(width / 2.0)
+    } else if width > height {
+      let hl = (width - height) / 2.0;
+      ApertureShape {
+        vertices: vec!(Vec2::new(-hl, 0.0), Vec2::new(hl, 0.0)),
+        radius: height / 2.0
+      }
+    } else {
+      let hl = (height - width) / 2.0;
+      ApertureShape {
+        vertices: vec!(Vec2::new(0.0, -hl), Vec2::new(0.0, hl)),
+        radius: width / 2.0
+      }
+    }
+  }
+
+  /// Regular polygon, `rotation` is in degrees as in aperture definition.
+  pub fn polygon(outer_diameter: f32, number_of_vertices: i32, rotation: f32) -> Self {
+    let n = number_of_vertices.max(3);
+    let radius = outer_diameter / 2.0;
+    let start = rotation.to_radians();
+    ApertureShape {
+      vertices: (0..n).map(|k| {
+        let (y, x) = (start + 2.0 * PI * k as f32 / n as f32).sin_cos();
+        radius * Vec2::new(x, y)
+      }).collect(),
+      radius: 0.0
+    }
+  }
+
+  pub fn vertices(&self) -> &Vec<Vec2> {
+    &self.vertices
+  }
+
+  pub fn radius(&self) -> f32 {
+    self.radius
+  }
+
+  /// Radius of the smallest circle around aperture origin, containing the aperture.
+  pub fn circumradius(&self) -> f32 {
+    self.vertices.iter().map(|v| v.magnitude()).fold(0.0, f32::max) + self.radius
+  }
+
+  fn prev(&self, vertex: usize) -> usize {
+    (vertex + self.vertices.len() - 1) % self.vertices.len()
+  }
+
+  fn edge_normal_angle(&self, edge: usize) -> f32 {
+    let from = self.vertices[edge];
+    let to = self.vertices[(edge + 1) % self.vertices.len()];
+    let d = to - from;
+    (-d.x).atan2(d.y)
+  }
+
+  /// Angles of outward edge normals. Support vertex changes only when normal of
+  /// stroke passes one of them.
+  pub fn critical_angles(&self) -> Vec<f32> {
+    if self.vertices.len() == 1 {
+      Vec::new()
+    } else {
+      (0..self.vertices.len()).map(|e| self.edge_normal_angle(e)).collect()
+    }
+  }
+
+  /// Vertex of the polygon, which lies furthest in `normal` direction.
+  /// When `normal` is one of the edge normals, the edge's end vertex is taken.
+  pub fn vertex_for(&self, normal: &Vec2) -> usize {
+    self.find_vertex(normal, false)
+  }
+
+  /// Same as `vertex_for`, but takes the edge's start vertex for edge normals.
+  pub fn ccw_vertex_for(&self, normal: &Vec2) -> usize {
+    self.find_vertex(normal, true)
+  }
+
+  fn find_vertex(&self, normal: &Vec2, ccw: bool) -> usize {
+    let len = self.vertices.len();
+    if len == 1 {
+      return 0;
+    }
+    let angle = angle_of(normal);
+    let found = (0..len).find(|vertex| {
+      let lower = self.edge_normal_angle(self.prev(*vertex));
+      let upper = self.edge_normal_angle(*vertex);
+      let span = cw_distance(upper, lower);
+      let d = cw_distance(angle, lower);
+      if ccw {
+        d < span - ANGLE_EPSILON
+      } else {
+        d > ANGLE_EPSILON && d <= span + ANGLE_EPSILON
+      }
+    });
+    found.unwrap_or_else(|| {
+      (0..len).max_by(|a, b| {
+        let a = self.vertices[*a].dot(normal);
+        let b = self.vertices[*b].dot(normal);
+        a.partial_cmp(&b).unwrap()
+      }).unwrap()
+    })
+  }
+
+  /// Point of the aperture outline for `vertex` when stroke normal is `normal`.
+  pub fn offset(&self, vertex: usize, normal: &Vec2) -> Vec2 {
+    self.vertices[vertex] + self.radius * normal
+  }
+
+  /// Whole aperture outline placed at `at`, traversed clockwise.
+  pub fn outline(&self, at: &Vec2) -> Vec<Box<dyn StrokePathElement>> {
+    let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
+    if self.vertices.len() == 1 {
+      let center = at + self.vertices[0];
+      let from = center + Vec2::new(self.radius, 0.0);
+      result.push(Box::new(Arc::new_with_fixed_center(from, from, center, CircularDirection::CW)));
+      return result;
+    }
+    let mut vertex = 0;
+    for _ in 0..self.vertices.len() {
+      let prev = self.prev(vertex);
+      let upper = direction_of(self.edge_normal_angle(vertex));
+      let lower = direction_of(self.edge_normal_angle(prev));
+      let edge_start = at + self.offset(vertex, &lower);
+      self.push_arc(&mut result, at + self.vertices[vertex], at + self.offset(vertex, &upper), edge_start);
+      result.push(Box::new(Line::new(at + self.offset(prev, &lower), edge_start)));
+      vertex = prev;
+    }
+    result
+  }
+
+  fn push_arc(&self, result: &mut Vec<Box<dyn StrokePathElement>>, center: Vec2, from: Vec2, to: Vec2) {
+    if self.radius > 0.0 && (to - from).magnitude() > f32::EPSILON {
+      result.push(Box::new(Arc::new_with_fixed_center(to, from, center, CircularDirection::CW)));
+    }
+  }
+
+  /// Walks aperture outline placed at `at` clockwise from `from_vertex` (stroke normal `from_normal`)
+  /// to `to_vertex` (stroke normal `to_normal`), turning not more than `sweep`.
+  /// Returns None if `to_vertex` cannot be reached this way.
+  pub fn walk_cw(
+    &self,
+    at: &Vec2,
+    from_vertex: usize,
+    from_normal: &Vec2,
+    to_vertex: usize,
+    to_normal: &Vec2,
+    sweep: f32
+  ) -> Option<Vec<Box<dyn StrokePathElement>>> {
+    let len = self.vertices.len();
+    let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
+    let mut vertex = from_vertex;
+    let mut angle = angle_of(from_normal);
+    let mut remaining = sweep;
+    let mut point = at + self.offset(vertex, from_normal);
+
+    if len > 1 {
+      for _ in 0..=2 * len {
+        let lower = self.edge_normal_angle(self.prev(vertex));
+        let step = cw_distance(angle, lower);
+        if vertex == to_vertex && step > remaining - ANGLE_EPSILON {
+          break;
+        }
+        if step > remaining + ANGLE_EPSILON {
+          return None;
+        }
+        let edge_normal = direction_of(lower);
+        let edge_start = at + self.offset(vertex, &edge_normal);
+        self.push_arc(&mut result, at + self.vertices[vertex], point, edge_start);
+        vertex = self.prev(vertex);
+        let edge_end = at + self.offset(vertex, &edge_normal);
+        result.push(Box::new(Line::new(edge_end, edge_start)));
+        point = edge_end;
+        angle = lower;
+        remaining -= step;
+      }
+    }
+
+    if vertex != to_vertex {
+      return None;
+    }
+    let end = at + self.offset(to_vertex, to_normal);
+    self.push_arc(&mut result, at + self.vertices[vertex], point, end);
+    Some(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rect_vertex_for_diagonal() {
+    let shape = ApertureShape::rect(2.0, 1.0);
+    let vertex = shape.vertex_for(&Vec2::new(-1.0, 1.0).normalize());
+    assert_eq!(shape.vertices()[vertex], Vec2::new(-1.0, 0.5));
+  }
+
+  #[test]
+  fn obround_vertex_for() {
+    let shape = ApertureShape::obround(3.0, 1.0);
+    assert_eq!(shape.radius(), 0.5);
+    let vertex = shape.vertex_for(&Vec2::new(1.0, 0.1).normalize());
+    assert_eq!(shape.vertices()[vertex], Vec2::new(1.0, 0.0));
+  }
+
+  #[test]
+  fn rect_vertices_for_edge_normal() {
+    let shape = ApertureShape::rect(2.0, 1.0);
+    let up = Vec2::new(0.0, 1.0);
+    assert_eq!(shape.vertices()[shape.vertex_for(&up)], Vec2::new(1.0, 0.5));
+    assert_eq!(shape.vertices()[shape.ccw_vertex_for(&up)], Vec2::new(-1.0, 0.5));
+  }
+
+  #[test]
+  fn walk_half_of_rect() {
+    let shape = ApertureShape::rect(2.0, 2.0);
+    let up = Vec2::new(0.0, 1.0);
+    let from = shape.vertex_for(&(-up));
+    let to = shape.vertex_for(&up);
+    let walk = shape.walk_cw(&Vec2::new(0.0, 0.0), from, &(-up), to, &up, PI).unwrap();
+    assert_eq!(walk.len(), 2);
+    assert_eq!(walk.last().unwrap().get_end_point(), shape.vertices()[to]);
+  }
+
+  #[test]
+  fn rect_outline() {
+    let shape = ApertureShape::rect(2.0, 1.0);
+    let outline = shape.outline(&Vec2::new(1.0, 1.0));
+    assert_eq!(outline.len(), 4);
+    assert_eq!(outline[0].get_start_point(), Vec2::new(0.0, 0.5));
+    assert_eq!(outline[0].get_end_point(), Vec2::new(0.0, 1.5));
+    assert_eq!(outline[3].get_end_point(), outline[0].get_start_point());
+  }
+
+  #[test]
+  fn obround_outline() {
+    let shape = ApertureShape::obround(2.0, 1.0);
+    let outline = shape.outline(&Vec2::new(0.0, 0.0));
+    assert_eq!(outline.len(), 4);
+    for ix in 0..outline.len() {
+      let next = &outline[(ix + 1) % outline.len()];
+      assert!((outline[ix].get_end_point() - next.get_start_point()).magnitude() < 1e-6);
+    }
+    assert_eq!(shape.circumradius(), 1.0);
+  }
+
+  #[test]
+  fn walk_half_of_circle() {
+    let shape = ApertureShape::circle(1.0);
+    let up = Vec2::new(0.0, 1.0);
+    let walk = shape.walk_cw(&Vec2::new(0.0, 0.0), 0, &(-up), 0, &up, PI).unwrap();
+    assert_eq!(walk.len(), 1);
+    assert_eq!(walk[0].get_end_point(), up);
+  }
+}
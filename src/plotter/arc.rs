@@ -6,6 +6,7 @@ use super::circular_direction::*;
 
 use super::intersector::{ Arc as SimpleArc, IntersectorEnum, Intersects };
 use na::{ Vector2, Rotation2 };
+use crate::parser::SourceSpan;
 
 type Vec2 = Vector2<f32>;
 #[derive(Debug, PartialEq, Clone)]
@@ -22,6 +23,7 @@ pub struct Arc {
   pub direction_in_start_point: Vec2,
   pub normal_in_end_point: Vec2,
   pub direction_in_end_point: Vec2,
+  pub source: Option<SourceSpan>
 }
 
 fn cmp(a: &f32, b: &f32) -> Ordering {
@@ -88,6 +90,66 @@ impl Arc {
     }
   }
 
+  pub fn sweep_to(&self, point: &Vec2) -> f32 {
+    let radial = (point - self.center).normalize();
+    let angle = Rotation2::rotation_between(&self.normal_in_start_point, &radial).angle();
+    let angle = match self.direction {
+      CircularDirection::CCW => angle,
+      CircularDirection::CW => -angle
+    };
+    angle.rem_euclid(2.0 * PI)
+  }
+
+  // Point of the arc, the sweep from its start point to which is `sweep`.
+  pub fn point_at(&self, sweep: f32) -> Vec2 {
+    let angle = match self.direction {
+      CircularDirection::CCW => sweep,
+      CircularDirection::CW => -sweep
+    };
+    self.center + Rotation2::new(angle) * (self.from - self.center)
+  }
+
+  // How many pieces the arc is cut into, so that none of them is further
+  // than `max_chord_error` from the arc.
+  fn pieces_count(&self, max_chord_error: f32, outside: bool) -> usize {
+    let radius = self.get_radius();
+    let cos = if outside {
+      radius / (radius + max_chord_error)
+    } else {
+      1.0 - max_chord_error / radius
+    };
+    let step = (2.0 * cos.clamp(0.0, 1.0).acos()).clamp(f32::EPSILON, PI / 2.0);
+    (self.angle_length / step).ceil().max(1.0) as usize
+  }
+
+  // Points of the polyline inscribed into the arc: all of them are on the arc
+  // and chords are inside of its circle.
+  pub fn to_polyline(&self, max_chord_error: f32) -> Vec<Vec2> {
+    let count = self.pieces_count(max_chord_error, false);
+    (0..=count)
+      .map(|ix| match ix {
+        0 => self.from,
+        ix if ix == count => self.to,
+        ix => self.point_at(self.angle_length * ix as f32 / count as f32)
+      })
+      .collect()
+  }
+
+  // Points of the polyline circumscribed around the arc: it starts and ends
+  // on the arc, all its segments are tangent to it and outside of its circle.
+  pub fn to_outer_polyline(&self, max_chord_error: f32) -> Vec<Vec2> {
+    let count = self.pieces_count(max_chord_error, true);
+    let step = self.angle_length / count as f32;
+    let scale = 1.0 / (step / 2.0).cos();
+    let mut points = vec!(self.from);
+    for ix in 0..count {
+      let point = self.point_at(step * (ix as f32 + 0.5));
+      points.push(self.center + (point - self.center) * scale);
+    }
+    points.push(self.to);
+    points
+  }
+
   pub fn is_between(&self, v: Vec2) ->bool {
     let kross = Self::kross(v - self.from, self.to - self.from); 
     match self.direction {
@@ -128,7 +190,8 @@ impl Arc {
       normal_in_end_point,
       angle_length,
       angle_start,
-      angle_end
+      angle_end,
+      source: None
     }
   }
 
@@ -187,16 +250,10 @@ impl PathElement for Arc {
     }
   }
   fn get_direction_in_start_point(&self) -> Vec2 {
-    match self.direction {
-      CircularDirection::CW => self.direction_in_start_point,
-      CircularDirection::CCW => -self.direction_in_start_point
-    }
+    self.direction_in_start_point
   }
   fn get_direction_in_end_point(&self) -> Vec2 {
-    match self.direction {
-      CircularDirection::CW => self.direction_in_end_point,
-      CircularDirection::CCW => -self.direction_in_end_point
-    }
+    self.direction_in_end_point
   }
 }
 
@@ -211,3 +268,34 @@ impl Intersects for Arc {
     })
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn arc_polylines_are_within_error() {
+    let arc = Arc::new_with_fixed_center(
+      Vec2::new(-10.0, 0.0),
+      Vec2::new(10.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      CircularDirection::CW
+    );
+    let error = 0.001;
+    let inner = arc.to_polyline(error);
+    let outer = arc.to_outer_polyline(error);
+    assert!(inner.len() > 10);
+    for points in [&inner, &outer] {
+      assert_eq!(points[0], arc.from);
+      assert_eq!(*points.last().unwrap(), arc.to);
+      assert!(points.iter().all(|point| point.y <= 1e-4));
+      for pair in points.windows(2) {
+        let middle = 0.5 * (pair[0] + pair[1]);
+        let distance = 10.0 - middle.magnitude();
+        assert!(distance.abs() <= error * 1.01);
+      }
+    }
+    assert!(inner.iter().all(|point| (point.magnitude() - 10.0).abs() < 1e-4));
+    assert!(outer.iter().all(|point| point.magnitude() >= 10.0 - 1e-4));
+  }
+}
@@ -0,0 +1,194 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use std::f32::consts::PI;
+use super::{
+  AlgebraicPathElement,
+  Arc,
+  CircularDirection,
+  Path,
+  StrokePathElement,
+  Tolerance,
+};
+
+type Vec2 = Vector2<f32>;
+
+// Fewer lines than that are rather corners, than approximated arcs.
+const MIN_FITTED_LINES: usize = 3;
+
+fn kross(a: &Vec2, b: &Vec2) -> f32 {
+  a.x * b.y - a.y * b.x
+}
+
+fn circumcenter(a: &Vec2, b: &Vec2, c: &Vec2) -> Option<Vec2> {
+  let (ab, ac) = (b - a, c - a);
+  let d = 2.0 * kross(&ab, &ac);
+  if d.abs() <= f32::EPSILON {
+    return None;
+  }
+  let (ab2, ac2) = (ab.dot(&ab), ac.dot(&ac));
+  Some(a + Vec2::new(ac.y * ab2 - ab.y * ac2, ab.x * ac2 - ac.x * ab2) / d)
+}
+
+// Arc through all the points, which are consecutive vertices of a polyline.
+fn fit(points: &[Vec2], tolerance: &Tolerance, max_chord_error: f32) -> Option<Arc> {
+  let count = points.len();
+  if count < MIN_FITTED_LINES + 1 {
+    return None;
+  }
+  let first = points[0];
+  let last = points[count - 1];
+  let center = if tolerance.is_same_point(&first, &last) {
+    circumcenter(&first, &points[count / 3], &points[2 * count / 3])
+  } else {
+    circumcenter(&first, &points[count / 2], &last)
+  }?;
+  let radius = (first - center).magnitude();
+  let turn = kross(&(points[1] - points[0]), &(points[2] - points[1]));
+  let mut angle = 0.0;
+  for pair in points.windows(2) {
+    let (from, to) = (pair[0] - center, pair[1] - center);
+    let sweep = kross(&from, &to).atan2(from.dot(&to));
+    let half_chord = 0.5 * (to - from).magnitude();
+    let sagitta = radius - (radius * radius - half_chord * half_chord).max(0.0).sqrt();
+    if sweep * turn <= 0.0
+      || sagitta > max_chord_error
+      || !tolerance.is_zero(to.magnitude() - radius) {
+      return None;
+    }
+    angle += sweep.abs();
+  }
+  if angle > 2.0 * PI + 1e-4 {
+    return None;
+  }
+  let direction = if turn > 0.0 { CircularDirection::CCW } else { CircularDirection::CW };
+  Some(Arc::new_with_fixed_center(last, first, center, direction))
+}
+
+fn is_line(element: &dyn StrokePathElement) -> bool {
+  match element.algebraic() {
+    AlgebraicPathElement::Line(_) => true,
+    AlgebraicPathElement::Arc(_) => false
+  }
+}
+
+impl Path {
+  // Runs of lines, whose vertices are on a common circle and whose chords are
+  // not further than `max_chord_error` from it, are replaced by single arcs.
+  pub fn fit_arcs(&self, tolerance: &Tolerance, max_chord_error: f32) -> Path {
+    let count = self.elements.len();
+    // Closed contour starts at its sharpest corner, so that no run is cut in two
+    let shift = if count > 1 && self.is_locked(tolerance) {
+      (0..count)
+        .map(|ix| {
+          let prev = self.elements[(ix + count - 1) % count].get_direction_in_end_point();
+          let next = self.elements[ix].get_direction_in_start_point();
+          (ix, prev.dot(&next))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(ix, _)| ix)
+        .unwrap_or(0)
+    } else {
+      0
+    };
+    let elements: Vec<&dyn StrokePathElement> = (0..count)
+      .map(|ix| self.elements[(ix + shift) % count].as_ref())
+      .collect();
+
+    let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
+    let mut ix = 0;
+    while ix < count {
+      let mut best: Option<(usize, Arc)> = None;
+      let mut end = ix;
+      let mut points = vec!(elements[ix].get_start_point());
+      while end < count && is_line(elements[end]) {
+        points.push(elements[end].get_end_point());
+        end += 1;
+        if let Some(arc) = fit(&points, tolerance, max_chord_error) {
+          best = Some((end, arc));
+        } else if points.len() > MIN_FITTED_LINES + 1 {
+          break;
+        }
+      }
+      match best {
+        Some((end, arc)) => {
+          result.push(Box::new(Arc { source: elements[ix].get_source().cloned(), ..arc }));
+          ix = end;
+        },
+        None => {
+          let el = elements[ix];
+          result.push(el.create_forward_with(el.get_start_point(), el.get_end_point()));
+          ix += 1;
+        }
+      }
+    }
+    Path { tp: self.tp.clone(), elements: result }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::Line;
+
+  fn polyline(points: &[Vec2]) -> Vec<Box<dyn StrokePathElement>> {
+    points.windows(2)
+      .map(|pair| {
+        let line: Box<dyn StrokePathElement> = Box::new(Line::new(pair[1], pair[0]));
+        line
+      })
+      .collect()
+  }
+
+  fn circle_points(center: Vec2, radius: f32, from: f32, to: f32, count: usize) -> Vec<Vec2> {
+    (0..=count)
+      .map(|ix| {
+        let angle = from + (to - from) * ix as f32 / count as f32;
+        center + radius * Vec2::new(angle.cos(), angle.sin())
+      })
+      .collect()
+  }
+
+  #[test]
+  fn polygon_circle_becomes_full_arc() {
+    let mut points = circle_points(Vec2::new(3.0, 4.0), 2.0, 0.0, 2.0 * PI, 64);
+    points[64] = points[0];
+    let path = Path::stroke(polyline(&points)).fit_arcs(&Tolerance::new(1e-4), 0.01);
+    assert_eq!(path.elements.len(), 1);
+    match path.elements[0].algebraic() {
+      AlgebraicPathElement::Arc(arc) => {
+        assert!((arc.center - Vec2::new(3.0, 4.0)).magnitude() < 1e-3);
+        assert!((arc.get_radius() - 2.0).abs() < 1e-3);
+        assert_eq!(arc.direction, CircularDirection::CCW);
+      },
+      _ => panic!("arc expected")
+    }
+  }
+
+  #[test]
+  fn rounded_corner_is_fitted_and_sharp_corners_stay() {
+    // slot: two straight sides and a round end drawn by 16 lines
+    let mut points = vec!(Vec2::new(0.0, 1.0), Vec2::new(0.0, -1.0));
+    points.extend(circle_points(Vec2::new(5.0, 0.0), 1.0, -PI / 2.0, PI / 2.0, 16));
+    points.push(Vec2::new(0.0, 1.0));
+    let path = Path::stroke(polyline(&points)).fit_arcs(&Tolerance::new(1e-4), 0.01);
+    let arcs: Vec<Arc> = path.elements.iter()
+      .filter_map(|el| match el.algebraic() {
+        AlgebraicPathElement::Arc(arc) => Some(arc),
+        _ => None
+      })
+      .collect();
+    assert_eq!(path.elements.len(), 4);
+    assert_eq!(arcs.len(), 1);
+    assert!((arcs[0].angle_length - PI).abs() < 1e-3);
+    assert!((path.signed_area() - (10.0 + 0.5 * PI)).abs() < 1e-3);
+
+    let square = vec!(
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(0.0, 0.0)
+    );
+    assert_eq!(Path::stroke(polyline(&square)).fit_arcs(&Tolerance::new(1e-4), 0.01).elements.len(), 4);
+  }
+}
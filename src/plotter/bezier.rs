@@ -0,0 +1,107 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use std::f32::consts::PI;
+use super::{ Arc, CircularDirection };
+
+type Vec2 = Vector2<f32>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubicBezier {
+  pub from: Vec2,
+  pub from_control: Vec2,
+  pub to_control: Vec2,
+  pub to: Vec2
+}
+
+impl CubicBezier {
+  pub fn point_at(&self, t: f32) -> Vec2 {
+    let s = 1.0 - t;
+    self.from * (s * s * s)
+      + self.from_control * (3.0 * s * s * t)
+      + self.to_control * (3.0 * s * t * t)
+      + self.to * (t * t * t)
+  }
+}
+
+// Largest distance from the circle to the usual cubic approximation of its
+// arc of `angle` radians, for unit radius.
+fn radial_error(angle: f32) -> f32 {
+  let (sin, cos) = (angle / 4.0).sin_cos();
+  2.0 / 27.0 * sin.powi(6) / (cos * cos)
+}
+
+impl Arc {
+  // The fewest equal pieces, none of them longer than a quarter of circle,
+  // whose curves stay within `max_radial_error` from the arc.
+  pub fn to_cubic_beziers(&self, max_radial_error: f32) -> Vec<CubicBezier> {
+    let radius = self.get_radius();
+    let mut count = (self.angle_length / (PI / 2.0)).ceil().max(1.0) as usize;
+    while count < 1024 && radius * radial_error(self.angle_length / count as f32) > max_radial_error {
+      count += 1;
+    }
+    let step = self.angle_length / count as f32;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+    let tangent = |point: &Vec2| {
+      let radial = (point - self.center).normalize();
+      match self.direction {
+        CircularDirection::CCW => Vec2::new(-radial.y, radial.x),
+        CircularDirection::CW => Vec2::new(radial.y, -radial.x)
+      }
+    };
+    (0..count)
+      .map(|ix| {
+        let from = if ix == 0 { self.from } else { self.point_at(step * ix as f32) };
+        let to = if ix + 1 == count { self.to } else { self.point_at(step * (ix + 1) as f32) };
+        CubicBezier {
+          from,
+          from_control: from + tangent(&from) * handle,
+          to_control: to - tangent(&to) * handle,
+          to
+        }
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn max_deviation(arc: &Arc, curves: &[CubicBezier]) -> f32 {
+    curves.iter()
+      .flat_map(|curve| (0..=100).map(move |ix| curve.point_at(ix as f32 / 100.0)))
+      .map(|point| ((point - arc.center).magnitude() - arc.get_radius()).abs())
+      .fold(0.0, f32::max)
+  }
+
+  #[test]
+  fn full_circle_is_four_curves() {
+    let center = Vec2::new(10.0, 5.0);
+    let start = Vec2::new(12.0, 5.0);
+    let arc = Arc::new_with_fixed_center(start, start, center, CircularDirection::CW);
+    let curves = arc.to_cubic_beziers(0.001);
+    assert_eq!(curves.len(), 4);
+    assert_eq!(curves[0].from, start);
+    assert_eq!(curves[3].to, start);
+    assert!((curves[0].to - Vec2::new(10.0, 3.0)).magnitude() < 1e-5);
+    assert!(max_deviation(&arc, &curves) < 0.001);
+  }
+
+  #[test]
+  fn error_bound_adds_pieces() {
+    let arc = Arc::new_with_fixed_center(
+      Vec2::new(0.0, -5.0),
+      Vec2::new(5.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      CircularDirection::CCW
+    );
+    let coarse = arc.to_cubic_beziers(0.01);
+    let fine = arc.to_cubic_beziers(0.0001);
+    assert_eq!(coarse.len(), 3);
+    assert!(fine.len() > coarse.len());
+    assert!(max_deviation(&arc, &fine) < 0.0001);
+    for pair in fine.windows(2) {
+      assert_eq!(pair[0].to, pair[1].from);
+    }
+  }
+}
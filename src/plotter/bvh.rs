@@ -0,0 +1,195 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+type Vec2 = Vector2<f32>;
+
+pub type BoundingBox = (Vec2, Vec2);
+
+const LEAF_SIZE: usize = 4;
+
+enum Node {
+  Leaf(usize, usize),
+  Branch(usize, usize)
+}
+
+// Bounding volume hierarchy over boxes of some items, which are known by
+// their indices in the slice the hierarchy was built from.
+pub struct Bvh {
+  nodes: Vec<(BoundingBox, Node)>,
+  items: Vec<usize>,
+  boxes: Vec<BoundingBox>
+}
+
+pub fn union_box(one: &BoundingBox, two: &BoundingBox) -> BoundingBox {
+  (
+    Vec2::new(one.0.x.min(two.0.x), one.0.y.min(two.0.y)),
+    Vec2::new(one.1.x.max(two.1.x), one.1.y.max(two.1.y))
+  )
+}
+
+pub fn overlaps(one: &BoundingBox, two: &BoundingBox) -> bool {
+  one.0.x <= two.1.x && two.0.x <= one.1.x && one.0.y <= two.1.y && two.0.y <= one.1.y
+}
+
+// Gap between two boxes, zero if they overlap.
+pub fn box_distance(one: &BoundingBox, two: &BoundingBox) -> f32 {
+  let dx = (two.0.x - one.1.x).max(one.0.x - two.1.x).max(0.0);
+  let dy = (two.0.y - one.1.y).max(one.0.y - two.1.y).max(0.0);
+  dx.hypot(dy)
+}
+
+impl Bvh {
+  pub fn new(boxes: &[BoundingBox]) -> Self {
+    let mut bvh = Bvh {
+      nodes: Vec::new(),
+      items: (0..boxes.len()).collect(),
+      boxes: boxes.to_vec()
+    };
+    if !boxes.is_empty() {
+      bvh.build(0, boxes.len());
+    }
+    bvh
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.nodes.is_empty()
+  }
+
+  // Items are split in halves along the longer side of their box, until
+  // there are few enough of them for a leaf. Returns index of the node.
+  fn build(&mut self, start: usize, end: usize) -> usize {
+    let boxes = &self.boxes;
+    let bounds = self.items[start..end].iter()
+      .map(|ix| boxes[*ix])
+      .fold(boxes[self.items[start]], |acc, b| union_box(&acc, &b));
+    let node = self.nodes.len();
+    if end - start <= LEAF_SIZE {
+      self.nodes.push((bounds, Node::Leaf(start, end)));
+      return node;
+    }
+    let size = bounds.1 - bounds.0;
+    let axis = if size.x >= size.y { 0 } else { 1 };
+    self.items[start..end].sort_by(|a, b| {
+      let a = boxes[*a].0[axis] + boxes[*a].1[axis];
+      let b = boxes[*b].0[axis] + boxes[*b].1[axis];
+      a.partial_cmp(&b).unwrap()
+    });
+    self.nodes.push((bounds, Node::Leaf(start, end)));
+    let middle = (start + end) / 2;
+    let left = self.build(start, middle);
+    let right = self.build(middle, end);
+    self.nodes[node].1 = Node::Branch(left, right);
+    node
+  }
+
+  // Items, whose boxes overlap the window.
+  pub fn in_window(&self, window: &BoundingBox) -> Vec<usize> {
+    let mut found = Vec::new();
+    let mut stack = if self.is_empty() { Vec::new() } else { vec!(0) };
+    while let Some(node) = stack.pop() {
+      let (node_bounds, kind) = &self.nodes[node];
+      if !overlaps(node_bounds, window) {
+        continue;
+      }
+      match kind {
+        Node::Leaf(start, end) => found.extend(
+          self.items[*start..*end].iter().filter(|item| overlaps(&self.boxes[**item], window))
+        ),
+        Node::Branch(left, right) => {
+          stack.push(*left);
+          stack.push(*right);
+        }
+      }
+    }
+    found
+  }
+
+  // Smallest value `measure` gives for items, whose boxes are closer to
+  // `bounds` than the best value found so far. Nearer nodes are visited first,
+  // so most of the hierarchy is never looked at.
+  pub fn nearest<F: FnMut(usize) -> f32>(&self, bounds: &BoundingBox, mut measure: F) -> Option<(usize, f32)> {
+    let mut best: Option<(usize, f32)> = None;
+    let mut stack = if self.is_empty() { Vec::new() } else { vec!(0) };
+    while let Some(node) = stack.pop() {
+      let (node_bounds, kind) = &self.nodes[node];
+      let limit = best.map(|(_, value)| value).unwrap_or(f32::INFINITY);
+      if limit <= 0.0 {
+        break;
+      }
+      if box_distance(node_bounds, bounds) > limit {
+        continue;
+      }
+      match kind {
+        Node::Leaf(start, end) => {
+          for item in self.items[*start..*end].iter() {
+            let limit = best.map(|(_, value)| value).unwrap_or(f32::INFINITY);
+            if box_distance(&self.boxes[*item], bounds) > limit {
+              continue;
+            }
+            let value = measure(*item);
+            if best.map(|(_, best)| value < best).unwrap_or(true) {
+              best = Some((*item, value));
+            }
+          }
+        },
+        Node::Branch(left, right) => {
+          let left_distance = box_distance(&self.nodes[*left].0, bounds);
+          let right_distance = box_distance(&self.nodes[*right].0, bounds);
+          if left_distance < right_distance {
+            stack.push(*right);
+            stack.push(*left);
+          } else {
+            stack.push(*left);
+            stack.push(*right);
+          }
+        }
+      }
+    }
+    best
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn unit_box(x: f32, y: f32) -> BoundingBox {
+    (Vec2::new(x, y), Vec2::new(x + 1.0, y + 1.0))
+  }
+
+  #[test]
+  fn nearest_box_visits_few_items() {
+    let boxes: Vec<BoundingBox> = (0..100)
+      .flat_map(|i| (0..100).map(move |j| unit_box(i as f32 * 2.0, j as f32 * 2.0)))
+      .collect();
+    let bvh = Bvh::new(&boxes);
+    let probe = unit_box(50.0, 60.6);
+    let mut visited = 0;
+    let found = bvh.nearest(&probe, |ix| {
+      visited += 1;
+      box_distance(&boxes[ix], &probe)
+    });
+    assert_eq!(found.map(|(ix, _)| boxes[ix].0), Some(Vec2::new(50.0, 60.0)));
+    assert!(visited < 100);
+  }
+
+  #[test]
+  fn boxes_in_window() {
+    let boxes: Vec<BoundingBox> = (0..30)
+      .flat_map(|i| (0..30).map(move |j| unit_box(i as f32 * 2.0, j as f32 * 2.0)))
+      .collect();
+    let bvh = Bvh::new(&boxes);
+    let window = (Vec2::new(3.5, 3.5), Vec2::new(8.5, 4.5));
+    let mut found = bvh.in_window(&window);
+    found.sort();
+    let expected: Vec<usize> = (0..boxes.len()).filter(|ix| overlaps(&boxes[*ix], &window)).collect();
+    assert_eq!(found, expected);
+    assert_eq!(found.len(), 3);
+  }
+
+  #[test]
+  fn empty_hierarchy() {
+    let bvh = Bvh::new(&[]);
+    assert!(bvh.nearest(&unit_box(0.0, 0.0), |_| 0.0).is_none());
+  }
+}
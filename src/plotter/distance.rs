@@ -0,0 +1,247 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use super::{
+  bounds,
+  AlgebraicPathElement,
+  Arc,
+  Bvh,
+  Line,
+  Region,
+  StrokePathElement,
+  Tolerance,
+};
+
+type Vec2 = Vector2<f32>;
+
+// Distance and the two nearest points, the first one is on the first item.
+pub type Clearance = (f32, Vec2, Vec2);
+
+fn nearest_of(candidates: Vec<(Vec2, Vec2)>) -> Clearance {
+  candidates.into_iter()
+    .map(|(one, two)| ((one - two).magnitude(), one, two))
+    .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+    .unwrap()
+}
+
+fn swapped((distance, one, two): Clearance) -> Clearance {
+  (distance, two, one)
+}
+
+fn nearest_on_line(line: &Line, point: &Vec2) -> Vec2 {
+  let dir = line.to - line.from;
+  let length = dir.dot(&dir);
+  if length <= f32::EPSILON {
+    return line.from;
+  }
+  let t = ((point - line.from).dot(&dir) / length).clamp(0.0, 1.0);
+  line.from + dir * t
+}
+
+// Point of the arc's circle in direction of `point`, if it is on the arc.
+fn radial_point(arc: &Arc, point: &Vec2) -> Option<Vec2> {
+  let radial = point - arc.center;
+  if radial.magnitude() <= f32::EPSILON {
+    return None;
+  }
+  let on_circle = arc.center + radial.normalize() * arc.get_radius();
+  if arc.is_between(on_circle) { Some(on_circle) } else { None }
+}
+
+fn nearest_on_arc(arc: &Arc, point: &Vec2) -> Vec2 {
+  let mut candidates = vec!((*point, arc.from), (*point, arc.to));
+  if let Some(on_arc) = radial_point(arc, point) {
+    candidates.push((*point, on_arc));
+  }
+  nearest_of(candidates).2
+}
+
+// Distance from the point to the element and the nearest point of the element.
+pub fn point_distance(element: &dyn StrokePathElement, point: &Vec2) -> (f32, Vec2) {
+  let nearest = match element.algebraic() {
+    AlgebraicPathElement::Line(line) => nearest_on_line(&line, point),
+    AlgebraicPathElement::Arc(arc) => nearest_on_arc(&arc, point)
+  };
+  ((nearest - point).magnitude(), nearest)
+}
+
+fn line_line(one: &Line, two: &Line) -> Clearance {
+  nearest_of(vec!(
+    (one.from, nearest_on_line(two, &one.from)),
+    (one.to, nearest_on_line(two, &one.to)),
+    (nearest_on_line(one, &two.from), two.from),
+    (nearest_on_line(one, &two.to), two.to)
+  ))
+}
+
+// Besides the ends, the nearest points may be where the radius, which is
+// perpendicular to the line, crosses both of them.
+fn line_arc(line: &Line, arc: &Arc) -> Clearance {
+  let mut candidates = vec!(
+    (line.from, nearest_on_arc(arc, &line.from)),
+    (line.to, nearest_on_arc(arc, &line.to)),
+    (nearest_on_line(line, &arc.from), arc.from),
+    (nearest_on_line(line, &arc.to), arc.to)
+  );
+  let foot = nearest_on_line(line, &arc.center);
+  if let Some(on_arc) = radial_point(arc, &foot) {
+    candidates.push((foot, on_arc));
+  }
+  nearest_of(candidates)
+}
+
+// Besides the ends, the nearest points may be on the line through both centers.
+fn arc_arc(one: &Arc, two: &Arc) -> Clearance {
+  let mut candidates = vec!(
+    (one.from, nearest_on_arc(two, &one.from)),
+    (one.to, nearest_on_arc(two, &one.to)),
+    (nearest_on_arc(one, &two.from), two.from),
+    (nearest_on_arc(one, &two.to), two.to)
+  );
+  let axis = two.center - one.center;
+  if axis.magnitude() > f32::EPSILON {
+    let axis = axis.normalize();
+    for sign_one in [-1.0, 1.0].iter() {
+      for sign_two in [-1.0, 1.0].iter() {
+        let on_one = one.center + axis * (one.get_radius() * sign_one);
+        let on_two = two.center + axis * (two.get_radius() * sign_two);
+        if one.is_between(on_one) && two.is_between(on_two) {
+          candidates.push((on_one, on_two));
+        }
+      }
+    }
+  }
+  nearest_of(candidates)
+}
+
+// Exact distance between two elements, zero where they touch.
+pub fn element_distance(one: &dyn StrokePathElement, two: &dyn StrokePathElement, tolerance: &Tolerance) -> Clearance {
+  let contacts = one.get_intersector().contacts(two.get_intersector(), tolerance.distance);
+  if let Some(point) = contacts.iter().flat_map(|contact| contact.points()).next() {
+    return (0.0, point, point);
+  }
+  match (one.algebraic(), two.algebraic()) {
+    (AlgebraicPathElement::Line(one), AlgebraicPathElement::Line(two)) => line_line(&one, &two),
+    (AlgebraicPathElement::Line(line), AlgebraicPathElement::Arc(arc)) => line_arc(&line, &arc),
+    (AlgebraicPathElement::Arc(arc), AlgebraicPathElement::Line(line)) => swapped(line_arc(&line, &arc)),
+    (AlgebraicPathElement::Arc(one), AlgebraicPathElement::Arc(two)) => arc_arc(&one, &two)
+  }
+}
+
+// Clearance between filled areas of two sets of regions: zero, if they overlap,
+// otherwise distance between their nearest boundaries. Boundaries of the
+// second set are indexed, so every element of the first one looks only at
+// the few nearest to it.
+pub fn distance(a: &[Region], b: &[Region], tolerance: &Tolerance) -> Option<Clearance> {
+  for (inner, outer) in [(a, b), (b, a)] {
+    for path in inner.iter().flat_map(|region| region.contours()) {
+      if let Some(element) = path.elements.first() {
+        let point = element.get_start_point();
+        if outer.iter().any(|region| region.contains(&point, tolerance)) {
+          return Some((0.0, point, point));
+        }
+      }
+    }
+  }
+
+  let elements: Vec<&dyn StrokePathElement> = b.iter()
+    .flat_map(|region| region.contours())
+    .flat_map(|path| path.elements.iter().map(|el| el.as_ref()))
+    .collect();
+  let boxes: Vec<_> = elements.iter().map(|el| bounds(*el, tolerance)).collect();
+  let index = Bvh::new(&boxes);
+
+  let mut best: Option<Clearance> = None;
+  for element in a.iter().flat_map(|region| region.contours()).flat_map(|path| path.elements.iter()) {
+    let element_box = bounds(element.as_ref(), tolerance);
+    let limit = best.map(|(distance, _, _)| distance).unwrap_or(f32::INFINITY);
+    let mut nearest: Option<Clearance> = None;
+    index.nearest(&element_box, |ix| {
+      let clearance = element_distance(element.as_ref(), elements[ix], tolerance);
+      if nearest.map(|(distance, _, _)| clearance.0 < distance).unwrap_or(true) {
+        nearest = Some(clearance);
+      }
+      clearance.0
+    });
+    if let Some(clearance) = nearest {
+      if clearance.0 < limit {
+        best = Some(clearance);
+      }
+    }
+  }
+  best
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Polarity;
+  use crate::plotter::{ tr, CircularDirection, Path };
+  use crate::plotter::fixtures::{ circle, circle_path, region, square, square_path };
+
+  fn assert_clearance(clearance: Clearance, distance: f32, one: Vec2, two: Vec2) {
+    assert!((clearance.0 - distance).abs() < 1e-4, "{:?}", clearance);
+    assert!((clearance.1 - one).magnitude() < 1e-4, "{:?}", clearance);
+    assert!((clearance.2 - two).magnitude() < 1e-4, "{:?}", clearance);
+  }
+
+  #[test]
+  fn distances_between_elements() {
+    let tolerance = Tolerance::default();
+    let line = Line::new(Vec2::new(5.0, 3.0), Vec2::new(-5.0, 3.0));
+    let arc = Arc::new_with_fixed_center(
+      Vec2::new(-1.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      CircularDirection::CCW
+    );
+    assert_clearance(element_distance(&line, &arc, &tolerance), 2.0, Vec2::new(0.0, 3.0), Vec2::new(0.0, 1.0));
+    assert_clearance(element_distance(&arc, &line, &tolerance), 2.0, Vec2::new(0.0, 1.0), Vec2::new(0.0, 3.0));
+
+    let lower = Arc::new_with_fixed_center(
+      Vec2::new(4.0, -2.0),
+      Vec2::new(4.0, 2.0),
+      Vec2::new(4.0, 0.0),
+      CircularDirection::CCW
+    );
+    assert_clearance(element_distance(&arc, &lower, &tolerance), 1.0, Vec2::new(1.0, 0.0), Vec2::new(2.0, 0.0));
+
+    let crossing = Line::new(Vec2::new(0.0, 5.0), Vec2::new(0.0, -5.0));
+    assert_eq!(element_distance(&crossing, &arc, &tolerance).0, 0.0);
+  }
+
+  #[test]
+  fn distance_between_regions() {
+    let tolerance = Tolerance::default();
+    let one = square(0.0, 0.0, 2.0);
+    let two = circle(5.0, 1.0, 1.0);
+    assert_eq!(one.distance(&two, &tolerance), Some(2.0));
+    let clearance = distance(std::slice::from_ref(&one), std::slice::from_ref(&two), &tolerance).unwrap();
+    assert_clearance(clearance, 2.0, Vec2::new(2.0, 1.0), Vec2::new(4.0, 1.0));
+
+    let inner = square(0.5, 0.5, 0.5);
+    assert_eq!(one.distance(&inner, &tolerance), Some(0.0));
+
+    // hole goes around the same way as the outline, nesting makes it a hole
+    let mut with_hole = tr(square_path(-5.0, -5.0, 10.0));
+    with_hole.push(circle_path(0.0, 0.0, 3.0));
+    let with_hole = Region::new(Polarity::Dark, with_hole);
+    let island = square(-1.0, -1.0, 2.0);
+    let clearance = with_hole.distance(&island, &tolerance).unwrap();
+    assert!((clearance - (3.0 - 2.0_f32.sqrt())).abs() < 1e-4);
+
+    let empty = region(Path::stroke(Vec::new()));
+    assert_eq!(empty.distance(&one, &tolerance), None);
+  }
+
+  #[test]
+  fn distance_across_layer() {
+    let tolerance = Tolerance::default();
+    let layer: Vec<Region> = (0..50)
+      .flat_map(|i| (0..50).map(move |j| square(i as f32 * 3.0, j as f32 * 3.0, 1.0)))
+      .collect();
+    let probe = vec!(circle(75.5, 200.0, 1.0));
+    let (distance, _, on_layer) = super::distance(&probe, &layer, &tolerance).unwrap();
+    assert!((distance - (200.0 - 148.0 - 1.0)).abs() < 1e-3);
+    assert!((on_layer - Vec2::new(75.5, 148.0)).magnitude() < 1e-3);
+  }
+}
@@ -0,0 +1,49 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use crate::parser::Polarity;
+use super::{ tr, Arc, CircularDirection, Line, Path, Region, StrokePathElement };
+
+type Vec2 = Vector2<f32>;
+
+// Shapes shared by tests. Contours go counter clockwise.
+
+pub fn polygon_path(points: &[Vec2]) -> Path {
+  let count = points.len();
+  Path::stroke((0..count)
+    .map(|ix| {
+      let side: Box<dyn StrokePathElement> = Box::new(Line::new(points[(ix + 1) % count], points[ix]));
+      side
+    })
+    .collect())
+}
+
+pub fn square_path(x: f32, y: f32, size: f32) -> Path {
+  polygon_path(&[
+    Vec2::new(x, y),
+    Vec2::new(x + size, y),
+    Vec2::new(x + size, y + size),
+    Vec2::new(x, y + size)
+  ])
+}
+
+pub fn circle_path(x: f32, y: f32, radius: f32) -> Path {
+  let center = Vec2::new(x, y);
+  let point = center + Vec2::new(radius, 0.0);
+  Path::stroke(vec!(Box::new(Arc::new_with_fixed_center(point, point, center, CircularDirection::CCW))))
+}
+
+pub fn region(path: Path) -> Region {
+  Region::new(Polarity::Dark, tr(path))
+}
+
+pub fn polygon(points: &[Vec2]) -> Region {
+  region(polygon_path(points))
+}
+
+pub fn square(x: f32, y: f32, size: f32) -> Region {
+  region(square_path(x, y, size))
+}
+
+pub fn circle(x: f32, y: f32, radius: f32) -> Region {
+  region(circle_path(x, y, radius))
+}
@@ -0,0 +1,404 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use std::f32::consts::PI;
+use super::{
+  AlgebraicPathElement,
+  Arc,
+  CircularDirection,
+  Line,
+  Path,
+  Region,
+  StrokePathElement,
+  Tolerance,
+};
+
+type Vec2 = Vector2<f32>;
+
+const ANGLE_EPSILON: f32 = 1e-5;
+// Steps to look for the least area between two orientations, where the
+// rectangle is flush with the hull.
+const RECTANGLE_SEARCH_STEPS: usize = 24;
+
+fn unit(angle: f32) -> Vec2 {
+  let (y, x) = angle.sin_cos();
+  Vec2::new(x, y)
+}
+
+fn kross(a: &Vec2, b: &Vec2) -> f32 {
+  a.x * b.y - a.y * b.x
+}
+
+// Part of the boundary, which may touch a supporting line: a point or an arc
+// going counterclockwise from `start` angle by `sweep`. Its furthest point in
+// direction of angle `a` is `center + radius * unit(a)`, while `a` is on the arc.
+#[derive(Clone, Copy, Debug)]
+struct Site {
+  center: Vec2,
+  radius: f32,
+  start: f32,
+  sweep: f32
+}
+
+impl Site {
+  fn point(point: Vec2) -> Self {
+    Site { center: point, radius: 0.0, start: 0.0, sweep: 2.0 * PI }
+  }
+
+  fn arc(arc: &Arc) -> Self {
+    let start = match arc.direction {
+      CircularDirection::CCW => arc.from,
+      CircularDirection::CW => arc.to
+    } - arc.center;
+    Site {
+      center: arc.center,
+      radius: arc.get_radius(),
+      start: start.y.atan2(start.x),
+      sweep: arc.angle_length
+    }
+  }
+
+  fn is_full(&self) -> bool {
+    self.sweep >= 2.0 * PI - ANGLE_EPSILON
+  }
+
+  // How far the site keeps supporting after the angle.
+  fn remaining(&self, angle: f32) -> Option<f32> {
+    if self.is_full() {
+      return Some(f32::INFINITY);
+    }
+    let mut passed = (angle - self.start).rem_euclid(2.0 * PI);
+    if passed > 2.0 * PI - ANGLE_EPSILON {
+      passed = 0.0;
+    }
+    if passed < self.sweep - ANGLE_EPSILON { Some(self.sweep - passed) } else { None }
+  }
+
+  fn support(&self, angle: f32) -> f32 {
+    self.center.dot(&unit(angle)) + self.radius
+  }
+
+  fn touch(&self, angle: f32) -> Vec2 {
+    self.center + unit(angle) * self.radius
+  }
+
+  // Angle after `angle`, where the other site starts to stick out further.
+  fn overtaken_by(&self, other: &Site, angle: f32) -> Option<f32> {
+    let shift = other.center - self.center;
+    let distance = shift.magnitude();
+    if distance <= f32::EPSILON {
+      return None;
+    }
+    let ratio = (self.radius - other.radius) / distance;
+    // circles touching from inside do not cross
+    if ratio.abs() >= 1.0 - ANGLE_EPSILON {
+      return None;
+    }
+    let crossing = shift.y.atan2(shift.x) - ratio.acos();
+    let delta = (crossing - angle).rem_euclid(2.0 * PI);
+    if delta > ANGLE_EPSILON { Some(delta) } else { None }
+  }
+}
+
+// The site supporting in the direction, of several equal ones the one going
+// further along the hull, and of these an arc rather than its end.
+fn supporting(sites: &[Site], angle: f32, tolerance: &Tolerance) -> usize {
+  let along = unit(angle + 0.5 * PI);
+  let key = |site: &Site| (site.support(angle), site.touch(angle).dot(&along), site.radius);
+  let mut best = 0;
+  let mut best_key = (f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+  for (ix, site) in sites.iter().enumerate() {
+    if site.remaining(angle).is_none() {
+      continue;
+    }
+    let site_key = key(site);
+    let is_better = if !tolerance.is_zero(site_key.0 - best_key.0) {
+      site_key.0 > best_key.0
+    } else if !tolerance.is_zero(site_key.1 - best_key.1) {
+      site_key.1 > best_key.1
+    } else {
+      site_key.2 > best_key.2
+    };
+    if is_better {
+      best = ix;
+      best_key = site_key;
+    }
+  }
+  best
+}
+
+// Points not on their convex hull cannot support it and are dropped, as well
+// as arcs lying inside of that hull.
+fn prune(points: Vec<Vec2>, arcs: Vec<Site>) -> Vec<Site> {
+  let mut points = points;
+  points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap().then(a.y.partial_cmp(&b.y).unwrap()));
+  points.dedup();
+  let chain = |ordered: &mut dyn Iterator<Item = &Vec2>| {
+    let mut chain: Vec<Vec2> = Vec::new();
+    for point in ordered {
+      while chain.len() >= 2
+        && kross(&(chain[chain.len() - 1] - chain[chain.len() - 2]), &(point - chain[chain.len() - 2])) <= 0.0 {
+        chain.pop();
+      }
+      chain.push(*point);
+    }
+    chain.pop();
+    chain
+  };
+  let mut hull = chain(&mut points.iter());
+  hull.extend(chain(&mut points.iter().rev()));
+  if hull.is_empty() {
+    hull = points;
+  }
+
+  let count = hull.len();
+  let is_inside = |site: &Site| {
+    count >= 3 && (0..count).all(|ix| {
+      let (from, to) = (hull[ix], hull[(ix + 1) % count]);
+      let side = to - from;
+      let normal = Vec2::new(side.y, -side.x);
+      let angle = normal.y.atan2(normal.x);
+      // ends of the arc are among the points
+      site.remaining(angle).is_none()
+        || site.support(angle) <= from.dot(&unit(angle))
+    })
+  };
+  let arcs: Vec<Site> = arcs.into_iter().filter(|site| !is_inside(site)).collect();
+  hull.into_iter().map(Site::point).chain(arcs).collect()
+}
+
+fn sites(paths: &[&Path]) -> Vec<Site> {
+  let mut points = Vec::new();
+  let mut arcs = Vec::new();
+  for element in paths.iter().flat_map(|path| path.elements.iter()) {
+    points.push(element.get_start_point());
+    points.push(element.get_end_point());
+    if let AlgebraicPathElement::Arc(arc) = element.algebraic() {
+      arcs.push(Site::arc(&arc));
+    }
+  }
+  prune(points, arcs)
+}
+
+// Counterclockwise contour of lines and arcs of the given ones, tangent to
+// them where arcs meet lines. None, if there are no elements.
+fn hull_of_sites(sites: &[Site], tolerance: &Tolerance) -> Option<Path> {
+  if sites.is_empty() {
+    return None;
+  }
+  let mut elements: Vec<Box<dyn StrokePathElement>> = Vec::new();
+  let mut angle = -0.5 * PI;
+  let mut turned = 0.0;
+  let mut current = supporting(sites, angle, tolerance);
+  while turned < 2.0 * PI - ANGLE_EPSILON {
+    let site = sites[current];
+    let mut delta = site.remaining(angle).unwrap_or(0.0).min(2.0 * PI - turned);
+    for (ix, other) in sites.iter().enumerate() {
+      if ix == current {
+        continue;
+      }
+      // another arc may start where the current site is its end
+      let start = (other.start - angle).rem_euclid(2.0 * PI);
+      let mut candidates = vec!();
+      if !other.is_full() && start > ANGLE_EPSILON
+        && tolerance.is_zero(other.support(angle + start) - site.support(angle + start)) {
+        candidates.push(start);
+      }
+      candidates.extend(site.overtaken_by(other, angle));
+      for candidate in candidates {
+        if candidate < delta && other.remaining(angle + candidate).is_some() {
+          delta = candidate;
+        }
+      }
+    }
+    let from = site.touch(angle);
+    let to = site.touch(angle + delta);
+    if site.radius > 0.0 && delta > ANGLE_EPSILON {
+      elements.push(Box::new(Arc::new_with_fixed_center(to, from, site.center, CircularDirection::CCW)));
+    }
+    angle += delta;
+    turned += delta;
+    current = supporting(sites, angle, tolerance);
+    let next = sites[current].touch(angle);
+    if !tolerance.is_same_point(&to, &next) {
+      elements.push(Box::new(Line::new(next, to)));
+    }
+  }
+  Some(Path::stroke(elements).simplified(tolerance))
+}
+
+// Exact convex hull of outer contours of the regions.
+pub fn convex_hull(regions: &[Region], tolerance: &Tolerance) -> Option<Path> {
+  let paths: Vec<&Path> = regions.iter().flat_map(|region| region.outer_contours()).collect();
+  hull_of_sites(&sites(&paths), tolerance)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrientedRectangle {
+  pub center: Vec2,
+  // Unit direction of the `size.x` sides.
+  pub axis: Vec2,
+  pub size: Vec2
+}
+
+impl OrientedRectangle {
+  pub fn area(&self) -> f32 {
+    self.size.x * self.size.y
+  }
+
+  // Counterclockwise, starting from the corner least along both sides.
+  pub fn corners(&self) -> [Vec2; 4] {
+    let u = self.axis * (0.5 * self.size.x);
+    let v = Vec2::new(-self.axis.y, self.axis.x) * (0.5 * self.size.y);
+    [self.center - u - v, self.center + u - v, self.center + u + v, self.center - u + v]
+  }
+}
+
+fn rectangle_at(sites: &[Site], angle: f32, tolerance: &Tolerance) -> OrientedRectangle {
+  let support = |angle: f32| sites[supporting(sites, angle, tolerance)].support(angle);
+  let (forward, backward) = (support(angle), support(angle + PI));
+  let (left, right) = (support(angle + 0.5 * PI), support(angle + 1.5 * PI));
+  let axis = unit(angle);
+  let normal = unit(angle + 0.5 * PI);
+  OrientedRectangle {
+    center: axis * (0.5 * (forward - backward)) + normal * (0.5 * (left - right)),
+    axis,
+    size: Vec2::new(forward + backward, left + right)
+  }
+}
+
+// The least rectangle is flush with a side of the hull, unless the hull has
+// arcs; then it may touch one of them anywhere, so orientations between the
+// sides are searched as well.
+fn min_area_rectangle_of(hull: &Path, tolerance: &Tolerance) -> OrientedRectangle {
+  let sites: Vec<Site> = hull.elements.iter()
+    .flat_map(|element| match element.algebraic() {
+      AlgebraicPathElement::Arc(arc) => vec!(Site::arc(&arc), Site::point(arc.from)),
+      AlgebraicPathElement::Line(line) => vec!(Site::point(line.from))
+    })
+    .collect();
+  let quarter = 0.5 * PI;
+  let mut flush: Vec<f32> = hull.elements.iter()
+    .filter_map(|element| match element.algebraic() {
+      AlgebraicPathElement::Line(line) => {
+        let side = line.to - line.from;
+        Some(side.y.atan2(side.x).rem_euclid(quarter))
+      },
+      AlgebraicPathElement::Arc(_) => None
+    })
+    .collect();
+  flush.sort_by(|a, b| a.partial_cmp(b).unwrap());
+  flush.dedup_by(|a, b| (*a - *b).abs() <= ANGLE_EPSILON);
+  if flush.is_empty() {
+    flush.push(0.0);
+  }
+
+  let area = |angle: f32| rectangle_at(&sites, angle, tolerance).area();
+  let has_arcs = sites.iter().any(|site| site.radius > 0.0);
+  let mut best = flush[0];
+  let mut best_area = area(best);
+  for (ix, start) in flush.iter().enumerate() {
+    if area(*start) < best_area {
+      best = *start;
+      best_area = area(*start);
+    }
+    if !has_arcs {
+      continue;
+    }
+    let end = if ix + 1 < flush.len() { flush[ix + 1] } else { flush[0] + quarter };
+    let step = (end - start) / RECTANGLE_SEARCH_STEPS as f32;
+    let (mut low, mut high) = (*start, end);
+    let lowest = (1..RECTANGLE_SEARCH_STEPS)
+      .map(|k| start + step * k as f32)
+      .min_by(|a, b| area(*a).partial_cmp(&area(*b)).unwrap());
+    if let Some(lowest) = lowest {
+      low = lowest - step;
+      high = lowest + step;
+    }
+    // golden section around the least of the samples
+    let ratio = 0.5 * (5.0_f32.sqrt() - 1.0);
+    while high - low > ANGLE_EPSILON {
+      let one = high - ratio * (high - low);
+      let two = low + ratio * (high - low);
+      if area(one) < area(two) { high = two; } else { low = one; }
+    }
+    let middle = 0.5 * (low + high);
+    if area(middle) < best_area {
+      best = middle;
+      best_area = area(middle);
+    }
+  }
+  rectangle_at(&sites, best, tolerance)
+}
+
+// Oriented rectangle of the least area, which covers the regions.
+pub fn min_area_rectangle(regions: &[Region], tolerance: &Tolerance) -> Option<OrientedRectangle> {
+  convex_hull(regions, tolerance).map(|hull| min_area_rectangle_of(&hull, tolerance))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::fixtures::{ circle, polygon };
+
+  fn counts(path: &Path) -> (usize, usize) {
+    path.elements.iter().fold((0, 0), |(lines, arcs), el| match el.algebraic() {
+      AlgebraicPathElement::Line(_) => (lines + 1, arcs),
+      AlgebraicPathElement::Arc(_) => (lines, arcs + 1)
+    })
+  }
+
+  #[test]
+  fn hull_of_polygons() {
+    let tolerance = Tolerance::default();
+    // concave "L" and a triangle apart from it
+    let regions = vec!(
+      polygon(&[
+        Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(2.0, 1.0),
+        Vec2::new(1.0, 1.0), Vec2::new(1.0, 2.0), Vec2::new(0.0, 2.0)
+      ]),
+      polygon(&[Vec2::new(4.0, 0.0), Vec2::new(5.0, 0.0), Vec2::new(4.0, 1.0)])
+    );
+    let hull = convex_hull(&regions, &tolerance).unwrap();
+    assert_eq!(counts(&hull), (5, 0));
+    assert!(hull.is_ccw());
+    assert!(hull.is_locked(&tolerance));
+    assert!((hull.signed_area() - 7.0).abs() < 1e-4);
+  }
+
+  #[test]
+  fn hull_keeps_arcs() {
+    let tolerance = Tolerance::default();
+    let regions = vec!(circle(0.0, 0.0, 1.0), circle(4.0, 0.0, 1.0));
+    let hull = convex_hull(&regions, &tolerance).unwrap();
+    assert_eq!(counts(&hull), (2, 2));
+    assert!((hull.signed_area() - (PI + 8.0)).abs() < 1e-3);
+    assert!((hull.perimeter() - (2.0 * PI + 8.0)).abs() < 1e-3);
+
+    let single = convex_hull(&[circle(1.0, 1.0, 2.0)], &tolerance).unwrap();
+    assert_eq!(counts(&single), (0, 1));
+
+    // small disk inside of the triangle does not change its hull
+    let regions = vec!(
+      polygon(&[Vec2::new(0.0, 0.0), Vec2::new(6.0, 0.0), Vec2::new(0.0, 6.0)]),
+      circle(1.0, 1.0, 0.5)
+    );
+    assert_eq!(counts(&convex_hull(&regions, &tolerance).unwrap()), (3, 0));
+  }
+
+  #[test]
+  fn least_rectangles() {
+    let tolerance = Tolerance::default();
+    // square turned by 30 degrees
+    let angle = PI / 6.0;
+    let corners: Vec<Vec2> = (0..4).map(|ix| unit(angle + ix as f32 * 0.5 * PI) * 2.0_f32.sqrt()).collect();
+    let rectangle = min_area_rectangle(&[polygon(&corners)], &tolerance).unwrap();
+    assert!((rectangle.area() - 4.0).abs() < 1e-4);
+    assert!(rectangle.center.magnitude() < 1e-4);
+    assert!(rectangle.axis.x.abs() > 0.1 && rectangle.axis.y.abs() > 0.1);
+
+    let regions = vec!(circle(0.0, 0.0, 1.0), circle(3.0, 3.0, 1.0));
+    let rectangle = min_area_rectangle(&regions, &tolerance).unwrap();
+    assert!((rectangle.area() - 2.0 * (3.0 * 2.0_f32.sqrt() + 2.0)).abs() < 1e-3);
+    assert!((rectangle.center - Vec2::new(1.5, 1.5)).magnitude() < 1e-3);
+  }
+}
@@ -48,6 +48,25 @@ pub struct Arc {
   pub direction: CircularDirection
 }
 
+// Geometric contact of two elements, found with some tolerance: crossing,
+// touching without crossing, or a common piece of collinear segments or
+// coincident arcs given by its ends.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Contact {
+  Point(Vec2),
+  Tangent(Vec2),
+  Overlap(Vec2, Vec2)
+}
+
+impl Contact {
+  pub fn points(&self) -> Vec<Vec2> {
+    match self {
+      Contact::Point(p) | Contact::Tangent(p) => vec!(*p),
+      Contact::Overlap(from, to) => vec!(*from, *to)
+    }
+  }
+}
+
 pub trait Intersects {
   fn get_intersector(&self) -> IntersectorEnum;
 }
@@ -100,6 +119,192 @@ impl IntersectorEnum {
     }
   }
 
+  pub fn contacts(&self, other: IntersectorEnum, tolerance: f32) -> Vec<Contact> {
+    use IntersectorEnum::*;
+    match (self, &other) {
+      (Segment(one), Segment(two)) => Self::segment_segment_contacts(one, two, tolerance),
+      (Segment(segment), Arc(arc)) | (Arc(arc), Segment(segment)) =>
+        Self::segment_arc_contacts(segment, arc, tolerance),
+      (Arc(one), Arc(two)) => Self::arc_arc_contacts(one, two, tolerance),
+      _ => self.intersects(other).into_iter().map(Contact::Point).collect()
+    }
+  }
+
+  fn distance_to_line(segment: &Segment, point: &Vec2) -> f32 {
+    let dir = (segment.to - segment.from).normalize();
+    Self::kross(point - segment.from, dir).abs()
+  }
+
+  // Offset of the point projection from segment start, in length units.
+  fn projection_on(segment: &Segment, point: &Vec2) -> f32 {
+    let dir = (segment.to - segment.from).normalize();
+    (point - segment.from).dot(&dir)
+  }
+
+  fn segment_segment_contacts(one: &Segment, two: &Segment, tolerance: f32) -> Vec<Contact> {
+    let one_length = (one.to - one.from).magnitude();
+    let two_length = (two.to - two.from).magnitude();
+    if one_length <= tolerance || two_length <= tolerance {
+      let (point, other) = if one_length <= tolerance { (one.from, two) } else { (two.from, one) };
+      let is_near = if (other.to - other.from).magnitude() <= tolerance {
+        (point - other.from).magnitude() <= tolerance
+      } else {
+        let along = Self::projection_on(other, &point);
+        Self::distance_to_line(other, &point) <= tolerance
+          && along >= -tolerance && along <= (other.to - other.from).magnitude() + tolerance
+      };
+      return if is_near { vec!(Contact::Point(point)) } else { Vec::new() };
+    }
+
+    let is_collinear = Self::distance_to_line(one, &two.from) <= tolerance
+      && Self::distance_to_line(one, &two.to) <= tolerance
+      && Self::distance_to_line(two, &one.from) <= tolerance
+      && Self::distance_to_line(two, &one.to) <= tolerance;
+
+    if is_collinear {
+      let dir = (one.to - one.from) / one_length;
+      let a = Self::projection_on(one, &two.from);
+      let b = Self::projection_on(one, &two.to);
+      let low = a.min(b).max(0.0);
+      let high = a.max(b).min(one_length);
+      if high - low > tolerance {
+        vec!(Contact::Overlap(one.from + dir * low, one.from + dir * high))
+      } else if high - low >= -tolerance {
+        vec!(Contact::Point(one.from + dir * (0.5 * (low + high))))
+      } else {
+        Vec::new()
+      }
+    } else {
+      match Self::linear_components(&one.make_ray(), &two.make_ray()) {
+        LinearIntersectResult::Parameters(s, t) => {
+          let one_margin = tolerance / one_length;
+          let two_margin = tolerance / two_length;
+          if s >= -one_margin && s <= 1.0 + one_margin && t >= -two_margin && t <= 1.0 + two_margin {
+            vec!(Contact::Point(one.spot(s.clamp(0.0, 1.0))))
+          } else {
+            Vec::new()
+          }
+        },
+        _ => Vec::new()
+      }
+    }
+  }
+
+  fn is_on_arc_with(arc: &Arc, point: &Vec2, tolerance: f32) -> bool {
+    (point - arc.from).magnitude() <= tolerance
+      || (point - arc.to).magnitude() <= tolerance
+      || Self::is_on_arc(arc, point)
+  }
+
+  fn is_on_segment_with(segment: &Segment, point: &Vec2, tolerance: f32) -> bool {
+    let along = Self::projection_on(segment, point);
+    along >= -tolerance && along <= (segment.to - segment.from).magnitude() + tolerance
+  }
+
+  fn segment_arc_contacts(segment: &Segment, arc: &Arc, tolerance: f32) -> Vec<Contact> {
+    let length = (segment.to - segment.from).magnitude();
+    let radius = (arc.from - arc.center).magnitude();
+    if length <= tolerance {
+      let on_circle = ((segment.from - arc.center).magnitude() - radius).abs() <= tolerance;
+      return if on_circle && Self::is_on_arc_with(arc, &segment.from, tolerance) {
+        vec!(Contact::Point(segment.from))
+      } else {
+        Vec::new()
+      };
+    }
+
+    let dir = (segment.to - segment.from) / length;
+    let foot = segment.from + dir * Self::projection_on(segment, &arc.center);
+    let distance = (foot - arc.center).magnitude();
+
+    let candidates = if (distance - radius).abs() <= tolerance {
+      let touch = if distance <= f32::EPSILON {
+        foot
+      } else {
+        arc.center + (foot - arc.center) * (radius / distance)
+      };
+      vec!(Contact::Tangent(touch))
+    } else if distance < radius {
+      let half_chord = (radius.powi(2) - distance.powi(2)).sqrt();
+      vec!(
+        Contact::Point(foot - dir * half_chord),
+        Contact::Point(foot + dir * half_chord)
+      )
+    } else {
+      Vec::new()
+    };
+
+    candidates.into_iter()
+      .filter(|contact| contact.points().iter().all(|p| {
+        Self::is_on_segment_with(segment, p, tolerance) && Self::is_on_arc_with(arc, p, tolerance)
+      }))
+      .collect()
+  }
+
+  // Arc as counterclockwise angular interval: starting angle and its length.
+  fn angular_interval(arc: &Arc, tolerance: f32) -> (f32, f32) {
+    let (start, end) = match arc.direction {
+      CircularDirection::CCW => (arc.from, arc.to),
+      CircularDirection::CW => (arc.to, arc.from)
+    };
+    let start = start - arc.center;
+    let end = end - arc.center;
+    let start_angle = start.y.atan2(start.x);
+    let length = if (end - start).magnitude() <= tolerance {
+      2.0 * PI
+    } else {
+      (end.y.atan2(end.x) - start_angle).rem_euclid(2.0 * PI)
+    };
+    (start_angle, length)
+  }
+
+  fn arc_arc_contacts(one: &Arc, two: &Arc, tolerance: f32) -> Vec<Contact> {
+    let one_radius = (one.from - one.center).magnitude();
+    let two_radius = (two.from - two.center).magnitude();
+    let u = two.center - one.center;
+    let distance = u.magnitude();
+
+    if distance <= tolerance && (one_radius - two_radius).abs() <= tolerance {
+      let angular_tolerance = tolerance / one_radius;
+      let (one_start, one_length) = Self::angular_interval(one, tolerance);
+      let (two_start, two_length) = Self::angular_interval(two, tolerance);
+      let shift = (two_start - one_start).rem_euclid(2.0 * PI);
+      let spot = |angle: f32| {
+        let (y, x) = (one_start + angle).sin_cos();
+        one.center + Vec2::new(x, y) * one_radius
+      };
+      let mut contacts = Vec::new();
+      for offset in [shift, shift - 2.0 * PI].iter() {
+        let low = offset.max(0.0);
+        let high = (offset + two_length).min(one_length);
+        if high - low > angular_tolerance {
+          contacts.push(Contact::Overlap(spot(low), spot(high)));
+        } else if high - low >= -angular_tolerance {
+          contacts.push(Contact::Point(spot(0.5 * (low + high))));
+        }
+      }
+      return contacts;
+    }
+    if distance <= tolerance {
+      return Vec::new();
+    }
+
+    let external = (distance - (one_radius + two_radius)).abs() <= tolerance;
+    let internal = (distance - (one_radius - two_radius).abs()).abs() <= tolerance;
+    let candidates = if external || internal {
+      let towards = if internal && two_radius > one_radius { -u / distance } else { u / distance };
+      vec!(Contact::Tangent(one.center + towards * one_radius))
+    } else {
+      Self::intersects_arc_arc_circles(one, two).into_iter().map(Contact::Point).collect()
+    };
+
+    candidates.into_iter()
+      .filter(|contact| contact.points().iter().all(|p| {
+        Self::is_on_arc_with(one, p, tolerance) && Self::is_on_arc_with(two, p, tolerance)
+      }))
+      .collect()
+  }
+
   fn ray_segment_intersection(ray: &Ray, segment: &Segment) -> Vec<Vec2>{
     match Self::linear_components(ray, &segment.make_ray()) {
       LinearIntersectResult::SameLines => Vec::new(),
@@ -225,6 +430,14 @@ impl IntersectorEnum {
   }
 
    fn intersects_arc_arc(left: &Arc, other: &Arc) -> Vec<Vec2> {
+    Self::intersects_arc_arc_circles(left, other).into_iter()
+      .filter(|p| {
+        IntersectorEnum::is_on_arc(left, p) && IntersectorEnum::is_on_arc(other, p)
+      })
+      .collect::<Vec<_>>()
+  }
+
+   fn intersects_arc_arc_circles(left: &Arc, other: &Arc) -> Vec<Vec2> {
     let u = other.center - left.center;
     let v = Vec2::new(u.y, -u.x);
     let r0 = (left.from - left.center).magnitude();
@@ -237,11 +450,7 @@ impl IntersectorEnum {
       let t = t_squared.abs().sqrt();
       let point1 = left.center + s * u + t * v;
       let point2 = left.center + s * u - t * v;
-      vec![point1, point2].into_iter()
-        .filter(|p| {
-          IntersectorEnum::is_on_arc(left, &p) && IntersectorEnum::is_on_arc(other, &p)
-        })
-        .collect::<Vec<_>>()
+      vec![point1, point2]
     } else {
       Vec::new()
     }
@@ -316,6 +525,7 @@ impl IntersectorEnum {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::plotter::DEFAULT_TOLERANCE;
 
   #[test]
   fn linear_components_same() {
@@ -382,5 +592,69 @@ mod tests {
     }
   }
 
-}
+  fn arc(center: Vec2, from: Vec2, to: Vec2, direction: CircularDirection) -> IntersectorEnum {
+    IntersectorEnum::Arc(Arc { center, from, to, direction })
+  }
 
+  fn segment(from: Vec2, to: Vec2) -> IntersectorEnum {
+    IntersectorEnum::Segment(Segment::new(from, to))
+  }
+
+  #[test]
+  fn collinear_segments_overlap() {
+    let one = segment(Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0));
+    let two = segment(Vec2::new(3.0, 0.000001), Vec2::new(1.0, 0.0));
+    assert_eq!(
+      one.contacts(two, DEFAULT_TOLERANCE),
+      vec!(Contact::Overlap(Vec2::new(1.0, 0.0), Vec2::new(3.0, 0.0)))
+    );
+  }
+
+  #[test]
+  fn segments_touch_end_to_end() {
+    let one = segment(Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0));
+    let two = segment(Vec2::new(2.0, 0.0), Vec2::new(3.0, 0.0));
+    assert_eq!(one.contacts(two, DEFAULT_TOLERANCE), vec!(Contact::Point(Vec2::new(2.0, 0.0))));
+
+    let one = segment(Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0));
+    let two = segment(Vec2::new(1.0, 0.000001), Vec2::new(1.0, 3.0));
+    assert_eq!(one.contacts(two, DEFAULT_TOLERANCE), vec!(Contact::Point(Vec2::new(1.0, 0.0))));
+  }
+
+  #[test]
+  fn segment_tangent_to_arc() {
+    let one = segment(Vec2::new(-2.0, 1.0), Vec2::new(2.0, 1.0));
+    let two = arc(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(-1.0, 0.0), CircularDirection::CCW);
+    assert_eq!(one.contacts(two, DEFAULT_TOLERANCE), vec!(Contact::Tangent(Vec2::new(0.0, 1.0))));
+
+    let one = segment(Vec2::new(-2.0, -1.0), Vec2::new(2.0, -1.0));
+    let two = arc(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(-1.0, 0.0), CircularDirection::CCW);
+    assert!(one.contacts(two, DEFAULT_TOLERANCE).is_empty());
+  }
+
+  #[test]
+  fn arcs_tangent() {
+    let one = arc(Vec2::new(0.0, 0.0), Vec2::new(0.0, -1.0), Vec2::new(0.0, 1.0), CircularDirection::CCW);
+    let two = arc(Vec2::new(2.0, 0.0), Vec2::new(2.0, 1.0), Vec2::new(2.0, -1.0), CircularDirection::CCW);
+    assert_eq!(one.contacts(two, DEFAULT_TOLERANCE), vec!(Contact::Tangent(Vec2::new(1.0, 0.0))));
+
+    let one = arc(Vec2::new(0.0, 0.0), Vec2::new(0.0, -2.0), Vec2::new(0.0, 2.0), CircularDirection::CCW);
+    let two = arc(Vec2::new(1.0, 0.0), Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0), CircularDirection::CCW);
+    assert_eq!(one.contacts(two, DEFAULT_TOLERANCE), vec!(Contact::Tangent(Vec2::new(2.0, 0.0))));
+  }
+
+  #[test]
+  fn coincident_arcs_overlap() {
+    let one = arc(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(-1.0, 0.0), CircularDirection::CCW);
+    let two = arc(Vec2::new(0.0, 0.0), Vec2::new(-1.0, 0.0), Vec2::new(0.0, 1.0), CircularDirection::CW);
+    let contacts = one.contacts(two, DEFAULT_TOLERANCE);
+    assert_eq!(contacts.len(), 1);
+    match &contacts[0] {
+      Contact::Overlap(from, to) => {
+        assert!((from - Vec2::new(0.0, 1.0)).magnitude() < 1e-6);
+        assert!((to - Vec2::new(-1.0, 0.0)).magnitude() < 1e-6);
+      },
+      other => panic!("unexpected contact {:?}", other)
+    }
+  }
+}
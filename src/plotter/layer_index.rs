@@ -0,0 +1,195 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use super::{
+  bounds,
+  point_distance,
+  BoundingBox,
+  Bvh,
+  Intersects,
+  Line,
+  Path,
+  Region,
+  StrokePathElement,
+  Tolerance,
+};
+use super::bvh::union_box;
+
+type Vec2 = Vector2<f32>;
+
+// Place of an element in the layer: index of the region, index of the contour
+// in `Region::contours` order and index of the element in the contour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ElementRef {
+  pub region: usize,
+  pub contour: usize,
+  pub element: usize
+}
+
+// Bounding volume hierarchies over regions of a layer and over all of their
+// elements, answering which of them are at a point or in a window.
+pub struct LayerIndex<'a> {
+  regions: &'a [Region],
+  contours: Vec<Vec<&'a Path>>,
+  elements: Vec<ElementRef>,
+  region_index: Bvh,
+  element_index: Bvh,
+  tolerance: Tolerance
+}
+
+impl<'a> LayerIndex<'a> {
+  pub fn new(regions: &'a [Region], tolerance: &Tolerance) -> Self {
+    let contours: Vec<Vec<&'a Path>> = regions.iter().map(|region| region.contours()).collect();
+    let mut elements = Vec::new();
+    let mut element_boxes = Vec::new();
+    let mut region_boxes = Vec::new();
+    for (region, paths) in contours.iter().enumerate() {
+      let mut region_box: Option<BoundingBox> = None;
+      for (contour, path) in paths.iter().enumerate() {
+        for (element, item) in path.elements.iter().enumerate() {
+          let element_box = bounds(item.as_ref(), tolerance);
+          region_box = Some(region_box.map(|b| union_box(&b, &element_box)).unwrap_or(element_box));
+          elements.push(ElementRef { region, contour, element });
+          element_boxes.push(element_box);
+        }
+      }
+      // Regions without elements are kept in place, but cannot be found
+      region_boxes.push(region_box.unwrap_or((
+        Vec2::new(f32::INFINITY, f32::INFINITY),
+        Vec2::new(f32::INFINITY, f32::INFINITY)
+      )));
+    }
+    LayerIndex {
+      regions,
+      contours,
+      elements,
+      region_index: Bvh::new(&region_boxes),
+      element_index: Bvh::new(&element_boxes),
+      tolerance: *tolerance
+    }
+  }
+
+  pub fn element(&self, element: &ElementRef) -> &'a dyn StrokePathElement {
+    self.contours[element.region][element.contour].elements[element.element].as_ref()
+  }
+
+  // Regions, whose filled area has the point.
+  pub fn regions_at(&self, point: &Vec2) -> Vec<usize> {
+    let mut found: Vec<usize> = self.region_index.in_window(&(*point, *point)).into_iter()
+      .filter(|ix| self.regions[*ix].contains(point, &self.tolerance))
+      .collect();
+    found.sort();
+    found
+  }
+
+  // Elements, which pass not further than `radius` from the point.
+  pub fn elements_at(&self, point: &Vec2, radius: f32) -> Vec<ElementRef> {
+    let margin = Vec2::new(radius, radius);
+    let mut found: Vec<ElementRef> = self.element_index.in_window(&(point - margin, point + margin)).into_iter()
+      .map(|ix| self.elements[ix])
+      .filter(|element| point_distance(self.element(element), point).0 <= radius)
+      .collect();
+    found.sort();
+    found
+  }
+
+  // The element nearest to the point and distance to it.
+  pub fn nearest_element(&self, point: &Vec2) -> Option<(ElementRef, f32)> {
+    self.element_index
+      .nearest(&(*point, *point), |ix| point_distance(self.element(&self.elements[ix]), point).0)
+      .map(|(ix, distance)| (self.elements[ix], distance))
+  }
+
+  // Elements, which have any point inside of the window or on its border.
+  pub fn elements_in(&self, min: &Vec2, max: &Vec2) -> Vec<ElementRef> {
+    let corners = [*min, Vec2::new(max.x, min.y), *max, Vec2::new(min.x, max.y)];
+    let sides: Vec<Line> = (0..4).map(|ix| Line::new(corners[(ix + 1) % 4], corners[ix])).collect();
+    let is_inside = |point: Vec2| {
+      point.x >= min.x && point.x <= max.x && point.y >= min.y && point.y <= max.y
+    };
+    let mut found: Vec<ElementRef> = self.element_index.in_window(&(*min, *max)).into_iter()
+      .map(|ix| self.elements[ix])
+      .filter(|element| {
+        let item = self.element(element);
+        is_inside(item.get_start_point()) || sides.iter().any(|side| {
+          !item.get_intersector().contacts(side.get_intersector(), self.tolerance.distance).is_empty()
+        })
+      })
+      .collect();
+    found.sort();
+    found
+  }
+
+  // Regions, whose filled area has any common point with the window.
+  pub fn regions_in(&self, min: &Vec2, max: &Vec2) -> Vec<usize> {
+    let center = 0.5 * (min + max);
+    let mut found: Vec<usize> = self.elements_in(min, max).into_iter()
+      .map(|element| element.region)
+      .collect();
+    found.extend(self.regions_at(&center));
+    found.sort();
+    found.dedup();
+    found
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Polarity;
+  use crate::plotter::tr;
+  use crate::plotter::fixtures::{ circle, square, square_path };
+
+  fn layer() -> Vec<Region> {
+    let mut regions: Vec<Region> = (0..20)
+      .flat_map(|i| (0..20).map(move |j| square(i as f32 * 3.0, j as f32 * 3.0, 2.0)))
+      .collect();
+    regions.push(circle(100.0, 100.0, 10.0));
+    // hole goes around the same way as its outline
+    let mut frame = tr(square_path(200.0, 200.0, 10.0));
+    frame.push(square_path(203.0, 203.0, 4.0));
+    regions.push(Region::new(Polarity::Dark, frame));
+    regions
+  }
+
+  #[test]
+  fn point_queries() {
+    let regions = layer();
+    let index = LayerIndex::new(&regions, &Tolerance::default());
+    assert_eq!(index.regions_at(&Vec2::new(31.0, 7.0)), vec!(10 * 20 + 2));
+    assert!(index.regions_at(&Vec2::new(32.5, 7.0)).is_empty());
+    assert_eq!(index.regions_at(&Vec2::new(100.0, 100.0)), vec!(400));
+    // inside the box of the circle, but not inside the circle
+    assert!(index.regions_at(&Vec2::new(109.0, 109.0)).is_empty());
+    assert_eq!(index.regions_at(&Vec2::new(201.0, 201.0)), vec!(401));
+    assert!(index.regions_at(&Vec2::new(205.0, 205.0)).is_empty());
+
+    let near = index.elements_at(&Vec2::new(32.1, 7.0), 0.2);
+    assert_eq!(near, vec!(ElementRef { region: 202, contour: 0, element: 1 }));
+    let (nearest, distance) = index.nearest_element(&Vec2::new(100.0, 95.0)).unwrap();
+    assert_eq!(nearest.region, 400);
+    assert!((distance - 5.0).abs() < 1e-4);
+  }
+
+  #[test]
+  fn window_queries() {
+    let regions = layer();
+    let index = LayerIndex::new(&regions, &Tolerance::default());
+    let found = index.regions_in(&Vec2::new(2.5, 2.5), &Vec2::new(3.5, 5.5));
+    assert_eq!(found, vec!(20 + 1));
+
+    // window inside of a region touches none of its elements
+    assert!(index.elements_in(&Vec2::new(99.0, 99.0), &Vec2::new(101.0, 101.0)).is_empty());
+    assert_eq!(index.regions_in(&Vec2::new(99.0, 99.0), &Vec2::new(101.0, 101.0)), vec!(400));
+
+    let brute: Vec<usize> = (0..regions.len())
+      .filter(|ix| {
+        let data = &regions[*ix].paths.data.elements[0];
+        let point = data.get_start_point();
+        point.x >= 10.0 && point.x <= 20.0 && point.y >= 10.0 && point.y <= 30.0
+      })
+      .collect();
+    let found = index.regions_in(&Vec2::new(10.0, 10.0), &Vec2::new(20.0, 30.0));
+    assert!(brute.iter().all(|ix| found.contains(ix)));
+    assert_eq!(found.len(), 4 * 8);
+  }
+}
@@ -3,6 +3,7 @@ use std::f32::consts::PI;
 use super::path_element::*;
 use super::intersector::{ Segment, IntersectorEnum, Intersects };
 use na::{Rotation2};
+use crate::parser::SourceSpan;
 
 
 #[derive(Debug, Clone, PartialEq)]
@@ -10,7 +11,8 @@ pub struct Line {
   pub to: Vec2,
   pub from: Vec2,
   pub(in super) normal: Vec2,
-  pub(in super) direction: Vec2
+  pub(in super) direction: Vec2,
+  pub source: Option<SourceSpan>
 }
 
 impl Line {
@@ -37,7 +39,8 @@ impl Line {
     };
     let normal = Rotation2::new(PI / 2.0) * direction;
     Line {
-      to, from, normal, direction
+      to, from, normal, direction,
+      source: None
     }
   }
 }
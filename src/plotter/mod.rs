@@ -6,24 +6,40 @@ mod ray;
 mod path;
 mod intersector;
 mod circular_direction;
-mod rect_path_helper;
 mod algebraic;
 mod region;
 mod stroke_path_element;
+mod aperture_shape;
 mod tree;
+mod sweep;
+mod tolerance;
+mod transform;
+mod bezier;
+mod bvh;
+mod distance;
+mod layer_index;
+mod arc_fit;
+mod hull;
+#[cfg(test)]
+mod fixtures;
 
 pub use algebraic::{ Algebraic, AlgebraicPathElement };
 
 pub use stroke_path_element::*;
+pub use aperture_shape::ApertureShape;
 pub use arc::Arc;
 pub use line::Line;
 pub use intersector::*;
 pub use region::*;
 pub use tree::*;
-pub use rect_path_helper::{
-  RectDir
-};
-
+pub use sweep::*;
+pub use tolerance::{ Tolerance, DEFAULT_TOLERANCE };
+pub use transform::{ Transform, ARC_APPROXIMATION_STEP };
+pub use bezier::CubicBezier;
+pub use bvh::{ Bvh, BoundingBox };
+pub use distance::{ distance, element_distance, point_distance, Clearance };
+pub use layer_index::{ ElementRef, LayerIndex };
+pub use hull::{ convex_hull, min_area_rectangle, OrientedRectangle };
 pub use path_element::{
   PathElement
 };
@@ -38,7 +54,10 @@ pub use path::{
 
 pub use plotter::{
   Plotter,
+  PlotterState,
   RawArc,
+  RawPath,
+  RawPathElement,
 };
 
 
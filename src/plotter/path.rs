@@ -1,9 +1,8 @@
 extern crate nalgebra as na;
-use super::StrokePathElement;
-use super::intersector::{ IntersectorEnum, Ray };
-use std::cmp::Ordering;
-use na::Vector2;
-use std::f32::EPSILON;
+use super::{ StrokePathElement, AlgebraicPathElement, Arc, CircularDirection, Line, Tolerance, Transform };
+use super::aperture_shape::ApertureShape;
+use na::{ Rotation2, Vector2 };
+use std::f32::consts::PI;
 
 type Vec2 = Vector2<f32>;
 
@@ -11,9 +10,23 @@ type Vec2 = Vector2<f32>;
 pub enum PathType {
   Rect(f32, f32),
   Circle(f32),
+  Obround(f32, f32),
+  Polygon(f32, i32, f32),
   Stroke
 }
 
+impl PathType {
+  pub fn aperture_shape(&self) -> ApertureShape {
+    match self {
+      PathType::Rect(w, h) => ApertureShape::rect(*w, *h),
+      PathType::Circle(radius) => ApertureShape::circle(*radius),
+      PathType::Obround(w, h) => ApertureShape::obround(*w, *h),
+      PathType::Polygon(diameter, vertices, rotation) => ApertureShape::polygon(*diameter, *vertices, *rotation),
+      PathType::Stroke => unreachable!("Region contour has no aperture")
+    }
+  }
+}
+
 #[derive(Debug)]
 pub struct Path{ 
   pub(in super) tp: PathType,
@@ -32,11 +45,32 @@ impl Path {
     self.elements.is_empty()
   }
 
-  pub fn is_locked(&self) -> bool {
+  pub fn is_locked(&self, tolerance: &Tolerance) -> bool {
     let first = self.elements.first().unwrap();
     let last = self.elements.last().unwrap();
-    let dist = first.get_start_point() - last.get_end_point();
-    dist.magnitude() <= EPSILON
+    tolerance.is_same_point(&first.get_start_point(), &last.get_end_point())
+  }
+
+  // Every element starts exactly where previous one ends, if their points are
+  // the same within tolerance. Contour is closed the same way.
+  pub fn weld(&mut self, tolerance: &Tolerance) {
+    let count = self.elements.len();
+    for ix in 1..count {
+      let end = self.elements[ix - 1].get_end_point();
+      let start = self.elements[ix].get_start_point();
+      if end != start && tolerance.is_same_point(&end, &start) {
+        let welded = self.elements[ix].create_forward_with(end, self.elements[ix].get_end_point());
+        self.elements[ix] = welded;
+      }
+    }
+    if count > 1 {
+      let start = self.elements[0].get_start_point();
+      let end = self.elements[count - 1].get_end_point();
+      if end != start && tolerance.is_same_point(&end, &start) {
+        let welded = self.elements[count - 1].create_forward_with(self.elements[count - 1].get_start_point(), start);
+        self.elements[count - 1] = welded;
+      }
+    }
   }
 
   pub fn stroke(els: Vec<Box<dyn StrokePathElement>>) -> Self {
@@ -50,63 +84,175 @@ impl Path {
     self.elements.push(element);
   }
 
-  pub fn is_point_inside(&self, point: &Vec2) -> bool {
-    /*
+  // Consecutive lines on one line and arcs on one circle are merged, elements
+  // of zero length are dropped. For closed contour the last and the first
+  // elements are merged as well.
+  pub fn simplified(&self, tolerance: &Tolerance) -> Path {
+    let mut elements: Vec<Box<dyn StrokePathElement>> = Vec::new();
+    // Vertices swallowed by each kept element, merged line has to stay close to all of them.
+    let mut absorbed: Vec<Vec<Vec2>> = Vec::new();
     for el in self.elements.iter() {
-      println!("path {} {}", el.get_start_point().x, el.get_start_point().y);
-    }
-    */
-
-    let ray = Ray::new(*point, point.normalize());
-    // println!("testing ray {}, {}", ray.origin, ray.dir);
-    let ray = IntersectorEnum::Ray(ray);
-    let mut intersects: Vec<Vec2> = self.elements.iter()
-      .enumerate()
-      .map(|(ix, el)| { 
-        let r_int = el.get_intersector();
-         // println!("WTF!!! {:?}", r_int);
-        let result = ray.intersects(el.get_intersector());
-        if !result.is_empty() {
-          // println!("element {} is around result {}", ix, result[0]);
+      if tolerance.is_zero(el.length()) {
+        continue;
+      }
+      let merged = elements.last()
+        .and_then(|last| merge(last.as_ref(), el.as_ref(), absorbed.last().unwrap(), tolerance));
+      match merged {
+        Some(merged) => {
+          let vertices = absorbed.last_mut().unwrap();
+          vertices.push(elements.last().unwrap().get_end_point());
+          *elements.last_mut().unwrap() = merged;
+        },
+        None => {
+          elements.push(el.create_forward_with(el.get_start_point(), el.get_end_point()));
+          absorbed.push(Vec::new());
         }
-        result
+      }
+    }
+    let mut path = Path { tp: self.tp.clone(), elements };
+    if path.elements.len() > 1 && path.is_locked(tolerance) {
+      let last = path.elements.len() - 1;
+      let vertices: Vec<Vec2> = absorbed[last].iter().chain(absorbed[0].iter()).cloned().collect();
+      if let Some(merged) = merge(path.elements[last].as_ref(), path.elements[0].as_ref(), &vertices, tolerance) {
+        path.elements[0] = merged;
+        path.elements.pop();
+      }
+    }
+    path.weld(tolerance);
+    path
+  }
 
-      })
+  // The same contour, which is gone around in the opposite direction.
+  pub fn reversed(&self) -> Path {
+    Path {
+      tp: self.tp.clone(),
+      elements: self.elements.iter().rev().map(|el| el.reversed()).collect()
+    }
+  }
 
-      .flatten()
-      .collect();
-     // println!("found intersects {:?}", intersects);
-    intersects.sort_unstable_by(|a, b| {
-        let c = a - b;
-        let magn = c.magnitude();
-        // println!("     magnitude {} {} ", magn, magn <= 1e-6 );
-        if magn <= 1e-6  {
-          Ordering::Equal
-         } else {
-          Ordering::Less
-        }
-      });
-    intersects.dedup_by(|a, b| {
-      let c = *a - *b;
-      let l = c.magnitude();
-      l <= 1e-6
-    });
+  pub fn is_ccw(&self) -> bool {
+    self.signed_area() > 0.0
+  }
 
+  // Aperture of drawn path is kept as it is, only its track is transformed.
+  pub fn transformed(&self, transform: &Transform) -> Path {
+    Path {
+      tp: self.tp.clone(),
+      elements: self.elements.iter()
+        .flat_map(|el| el.transformed(transform))
+        .collect()
+    }
+  }
 
-    let count = intersects.len();
+  // How many times closed contour goes around the point, counterclockwise is positive.
+  pub fn winding_number(&self, point: &Vec2, tolerance: &Tolerance) -> i32 {
+    let angle: f32 = self.elements.iter()
+      .map(|el| subtended_angle(el.as_ref(), point, tolerance))
+      .sum();
+    (angle / (2.0 * PI)).round() as i32
+  }
 
-    // println!("is_point_inside me {}>>>> {}; {}  (count: {})", self.elements.len(), point.x, point.y, count);
+  pub fn is_point_inside(&self, point: &Vec2, tolerance: &Tolerance) -> bool {
+    self.winding_number(point, tolerance) != 0
+  }
 
-    count % 2 != 0
-    
+  // Area of closed contour, positive for counterclockwise one. Arcs add or
+  // take away the circular segment between them and their chords.
+  pub fn signed_area(&self) -> f32 {
+    let origin = match self.elements.first() {
+      Some(el) => el.get_start_point(),
+      None => return 0.0
+    };
+    self.elements.iter()
+      .map(|el| {
+        let from = el.get_start_point() - origin;
+        let to = el.get_end_point() - origin;
+        let chord = 0.5 * (from.x * to.y - to.x * from.y);
+        match el.algebraic() {
+          AlgebraicPathElement::Line(_) => chord,
+          AlgebraicPathElement::Arc(arc) => {
+            let radius = arc.get_radius();
+            let angle = arc.angle_length;
+            let segment = 0.5 * radius * radius * (angle - angle.sin());
+            match arc.direction {
+              CircularDirection::CCW => chord + segment,
+              CircularDirection::CW => chord - segment
+            }
+          }
+        }
+      })
+      .sum()
+  }
+
+  pub fn perimeter(&self) -> f32 {
+    self.elements.iter().map(|el| el.length()).sum()
   }
 }
 
+// Single element, which goes the same way as `one` followed by `two`.
+// `absorbed` are vertices already merged into either of them.
+fn merge(one: &dyn StrokePathElement, two: &dyn StrokePathElement, absorbed: &[Vec2], tolerance: &Tolerance) -> Option<Box<dyn StrokePathElement>> {
+  match (one.algebraic(), two.algebraic()) {
+    (AlgebraicPathElement::Line(one), AlgebraicPathElement::Line(two)) => {
+      let chord = Line::new(two.to, one.from);
+      let deviation = absorbed.iter().chain(std::iter::once(&one.to))
+        .map(|vertex| {
+          let offset = vertex - one.from;
+          (chord.direction.x * offset.y - chord.direction.y * offset.x).abs()
+        })
+        .fold(0.0, f32::max);
+      if (one.to - one.from).dot(&(two.to - two.from)) > 0.0 && deviation <= tolerance.distance {
+        Some(Box::new(Line { source: one.source.clone(), ..chord }))
+      } else {
+        None
+      }
+    },
+    (AlgebraicPathElement::Arc(one), AlgebraicPathElement::Arc(two)) => {
+      let is_same_circle = one.direction == two.direction
+        && tolerance.is_same_point(&one.center, &two.center)
+        && tolerance.is_zero(one.get_radius() - two.get_radius());
+      if is_same_circle && one.angle_length + two.angle_length <= 2.0 * PI + 1e-4 {
+        Some(Box::new(Arc {
+          source: one.source.clone(),
+          ..Arc::new_with_fixed_center(two.to, one.from, one.center, one.direction.clone())
+        }))
+      } else {
+        None
+      }
+    },
+    _ => None
+  }
+}
+
+pub(crate) fn subtended_angle(element: &dyn StrokePathElement, point: &Vec2, tolerance: &Tolerance) -> f32 {
+  let from = element.get_start_point() - point;
+  let to = element.get_end_point() - point;
+  if tolerance.is_zero(from.magnitude()) || tolerance.is_zero(to.magnitude()) {
+    return 0.0;
+  }
+  let chord = Rotation2::rotation_between(&from, &to).angle();
+  match element.algebraic() {
+    AlgebraicPathElement::Line(_) => chord,
+    AlgebraicPathElement::Arc(arc) => {
+      let is_full = arc.angle_length >= 2.0 * PI - tolerance.angle(arc.get_radius());
+      let inside_segment = (point - arc.center).magnitude() < arc.get_radius()
+        && (is_full || arc.is_between(*point));
+      if !inside_segment {
+        chord
+      } else {
+        match arc.direction {
+          CircularDirection::CCW => chord + 2.0 * PI,
+          CircularDirection::CW => chord - 2.0 * PI
+        }
+      }
+    }
+  }
+}
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::plotter::{ Line, Vec2 };
+  use crate::plotter::Vec2;
 
   #[test]
   fn is_point_inside () {
@@ -118,6 +264,105 @@ mod tests {
     );
 
     let path = Path::stroke(elements);
-    assert_eq!(path.is_point_inside(&Vec2::new(1.0, 1.0)), true);
+    assert_eq!(path.is_point_inside(&Vec2::new(1.0, 1.0), &Tolerance::default()), true);
+  }
+
+  #[test]
+  fn weld_closes_gaps_within_tolerance() {
+    let elements: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Line::new(Vec2::new(400.0, 0.0), Vec2::new(0.0, 0.0))),
+      Box::new(Line::new(Vec2::new(400.0, 400.0), Vec2::new(400.00003, 0.0))),
+      Box::new(Line::new(Vec2::new(0.0, 0.00002), Vec2::new(400.0, 400.0))),
+    );
+    let mut path = Path::stroke(elements);
+    let tolerance = Tolerance::new(0.0001);
+    assert!(!path.is_locked(&Tolerance::new(f32::EPSILON)));
+    path.weld(&tolerance);
+    assert_eq!(path.elements[1].get_start_point(), Vec2::new(400.0, 0.0));
+    assert_eq!(path.elements[2].get_end_point(), Vec2::new(0.0, 0.0));
+    assert!(path.is_locked(&Tolerance::new(f32::EPSILON)));
+  }
+
+  #[test]
+  fn area_and_perimeter_of_rounded_contour() {
+    // 2x2 square with the right side replaced by a half circle
+    let elements: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Line::new(Vec2::new(102.0, 100.0), Vec2::new(100.0, 100.0))),
+      Box::new(Arc::new_with_fixed_center(
+        Vec2::new(102.0, 102.0),
+        Vec2::new(102.0, 100.0),
+        Vec2::new(102.0, 101.0),
+        CircularDirection::CCW
+      )),
+      Box::new(Line::new(Vec2::new(100.0, 102.0), Vec2::new(102.0, 102.0))),
+      Box::new(Line::new(Vec2::new(100.0, 100.0), Vec2::new(100.0, 102.0)))
+    );
+    let path = Path::stroke(elements);
+    assert!((path.signed_area() - (4.0 + 0.5 * PI)).abs() < 1e-4);
+    assert!((path.perimeter() - (6.0 + PI)).abs() < 1e-4);
+
+    let reversed = path.reversed();
+    assert!((reversed.signed_area() + path.signed_area()).abs() < 1e-4);
+  }
+
+  #[test]
+  fn simplified_merges_collinear_and_cocircular_elements() {
+    let center = Vec2::new(2.0, 3.0);
+    let elements: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Line::new(Vec2::new(3.0, 0.0), Vec2::new(1.0, 0.0))),
+      Box::new(Line::new(Vec2::new(3.0, 0.0), Vec2::new(3.0, 0.0))),
+      Box::new(Line::new(Vec2::new(4.0, 0.000001), Vec2::new(3.0, 0.0))),
+      Box::new(Line::new(Vec2::new(4.0, 1.0), Vec2::new(4.0, 0.000001))),
+      Box::new(Arc::new_with_fixed_center(Vec2::new(4.0, 3.0), Vec2::new(4.0, 1.0), Vec2::new(4.0, 2.0), CircularDirection::CCW)),
+      Box::new(Line::new(Vec2::new(0.0, 3.0), Vec2::new(4.0, 3.0))),
+      Box::new(Line::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 3.0))),
+      Box::new(Line::new(Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0))),
+    );
+    let path = Path::stroke(elements);
+    let simple = path.simplified(&Tolerance::default());
+    assert_eq!(simple.elements.len(), 5);
+    assert_eq!(simple.elements[0].get_start_point(), Vec2::new(0.0, 0.0));
+    assert_eq!(simple.elements[0].get_end_point(), Vec2::new(4.0, 0.000001));
+    assert!((simple.signed_area() - path.signed_area()).abs() < 1e-4);
+
+    let halves: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Arc::new_with_fixed_center(Vec2::new(0.0, 3.0), Vec2::new(4.0, 3.0), center, CircularDirection::CW)),
+      Box::new(Arc::new_with_fixed_center(Vec2::new(2.0, 5.0), Vec2::new(0.0, 3.0), center, CircularDirection::CW)),
+      Box::new(Arc::new_with_fixed_center(Vec2::new(4.0, 3.0), Vec2::new(2.0, 5.0), center, CircularDirection::CW)),
+    );
+    let circle = Path::stroke(halves).simplified(&Tolerance::default());
+    assert_eq!(circle.elements.len(), 1);
+    assert!((circle.signed_area() + 4.0 * PI).abs() < 1e-4);
+  }
+
+  #[test]
+  fn simplified_keeps_corners_and_turns_back() {
+    let elements: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Line::new(Vec2::new(2.0, 0.0), Vec2::new(0.0, 0.0))),
+      Box::new(Line::new(Vec2::new(1.0, 0.0), Vec2::new(2.0, 0.0))),
+      Box::new(Line::new(Vec2::new(1.0, 1.0), Vec2::new(1.0, 0.0))),
+    );
+    assert_eq!(Path::stroke(elements).simplified(&Tolerance::default()).elements.len(), 3);
+  }
+
+  #[test]
+  fn simplified_keeps_slow_curve_within_tolerance() {
+    // Every joint alone is within tolerance, the whole polyline is not.
+    let tolerance = Tolerance::new(0.01);
+    let points: Vec<Vec2> = (0..=20)
+      .map(|ix| ix as f32 * 0.1)
+      .map(|x| Vec2::new(x, 0.05 * x * x))
+      .collect();
+    let elements: Vec<Box<dyn StrokePathElement>> = points.windows(2)
+      .map(|pair| Box::new(Line::new(pair[1], pair[0])) as Box<dyn StrokePathElement>)
+      .collect();
+    let simple = Path::stroke(elements).simplified(&tolerance);
+    assert!(simple.elements.len() > 1);
+    for point in points.iter() {
+      let distance = simple.elements.iter()
+        .map(|el| crate::plotter::distance::point_distance(el.as_ref(), point).0)
+        .fold(f32::MAX, f32::min);
+      assert!(distance <= tolerance.distance + 1e-6);
+    }
   }
 }
@@ -7,7 +7,9 @@ use super::line::Line;
 use super::arc::Arc;
 use super::path::{ PathType, Path };
 use super::circular_direction::*;
-use super::region::Region;
+use super::region::{ validate_contour, FillRule, Region, RegionIssue };
+use super::StrokePathElement;
+use super::Tolerance;
 
 type Vec2 = Vector2<f32>;
 
@@ -21,7 +23,7 @@ pub struct RawArc{
 }
 
 #[derive(Clone, Debug, PartialEq)]
-enum RawPathElement {
+pub enum RawPathElement {
   SingleQuadrant{ x: Option<f32>, y: Option<f32>, i: Option<f32>, j: Option<f32>, dir: CircularDirection},
   MultiQuadrant{ x: Option<f32>, y: Option<f32>, i: Option<f32>, j: Option<f32>, dir: CircularDirection},
   Linear{x: Option<f32>, y: Option<f32>},
@@ -31,46 +33,62 @@ enum RawPathElement {
 
 
 #[derive(Debug, PartialEq)]
-struct RawPath {
+pub struct RawPath {
   tp: PathType,
   elements: Vec<RawPathElement>,
+  spans: Vec<Option<SourceSpan>>,
+  extra_span: Option<SourceSpan>
 }
 
 
 impl From<RawPath> for Path {
   fn from(path: RawPath) -> Self {
+    path.to_path(&Tolerance::default())
+  }
+}
+
+impl RawPath {
+  pub fn to_path(&self, tolerance: &Tolerance) -> Path {
     use RawPathElement::*;
-    let mut path_elements: Path = Path::new(path.tp);
+    let mut path_elements: Path = Path::new(self.tp.clone());
     let mut start_point: Option<Vec2> = None;
-    for element in path.elements.into_iter() {
-      match element {
-        Start{x, y} => {start_point.replace(Vec2::new(x.unwrap_or(0.0), y.unwrap_or(0.0)));},
+    for (element, span) in self.elements.iter().zip(self.spans.iter()) {
+      let mut path_element: Box<dyn StrokePathElement> = match element.clone() {
+        Start{x, y} => {
+          start_point.replace(Vec2::new(x.unwrap_or(0.0), y.unwrap_or(0.0)));
+          continue;
+        },
         Linear{ x, y } => {
           match start_point { 
             Some(from) => {
               let v = Vec2::new(x.unwrap_or(from.x), y.unwrap_or(from.y));
-              path_elements.add(Box::new(Line::new(v, from)));
               start_point.replace(v);
+              Box::new(Line::new(v, from))
             },
             None => unreachable!("Must be start_point")
-          };
+          }
         },
         SingleQuadrant{x, y, i, j, dir} => {
           match start_point { 
             Some(from) => {
               let to = Vec2::new(x.unwrap_or(from.x), y.unwrap_or(from.y));
               start_point.replace(to);
-              path_elements.add(Box::new(Arc::new(
-              to,
-              from,
-              i, 
-              j,
-              true,
-              dir
-            )))
+              if tolerance.is_same_point(&to, &from) {
+                // single quadrant arc cannot be full circle, so it has zero length
+                Box::new(Line::new(to, from))
+              } else {
+                Box::new(Arc::new(
+                to,
+                from,
+                i, 
+                j,
+                true,
+                dir
+              ))
+              }
             },
             None => unreachable!("Must be start_point")
-          };
+          }
         },
         MultiQuadrant{ x, y, i, j, dir } => {
           match start_point { 
@@ -78,19 +96,21 @@ impl From<RawPath> for Path {
               let to = Vec2::new(x.unwrap_or(from.x), y.unwrap_or(from.y));
               start_point.replace(to);
 
-              path_elements.add(Box::new(Arc::new(
+              Box::new(Arc::new(
               to,
               from,
               i, 
               j,
               false,
               dir
-            )))
+            ))
             },
             None => unreachable!("Must be start_point")
-          };
+          }
         }
-      }
+      };
+      path_element.set_source(span.clone());
+      path_elements.add(path_element);
     };
     path_elements
   }
@@ -105,26 +125,86 @@ impl RawPath {
   pub fn start(tp: PathType,) -> Self {
     RawPath {
       tp,
-      elements: Vec::new()
+      elements: Vec::new(),
+      spans: Vec::new(),
+      extra_span: None
     }
   }
 
-  pub fn push(&mut self, el: RawPathElement) {
+  pub fn push(&mut self, el: RawPathElement, span: Option<SourceSpan>) {
     self.elements.push(el);
+    self.spans.push(span);
+  }
+
+  fn extend_span(&mut self, span: Option<SourceSpan>) {
+    if let Some(span) = span {
+      let merged = match &self.extra_span {
+        Some(extra) => extra.merge(&span),
+        None => span
+      };
+      self.extra_span.replace(merged);
+    }
+  }
+
+  /// Spans of the commands, which produced elements
+  pub fn spans(&self) -> &Vec<Option<SourceSpan>> {
+    &self.spans
+  }
+
+  /// Span covering all commands of the path
+  pub fn span(&self) -> Option<SourceSpan> {
+    self.spans.iter().chain(std::iter::once(&self.extra_span))
+      .flatten()
+      .fold(None, |result: Option<SourceSpan>, span| match result {
+        Some(result) => Some(result.merge(span)),
+        None => Some(span.clone())
+      })
+  }
+
+  pub fn path_type(&self) -> &PathType {
+    &self.tp
+  }
+
+  pub fn elements(&self) -> &Vec<RawPathElement> {
+    &self.elements
   }
 }
 
+/// What plotter knows at the moment: modal settings and path, which is being collected.
+#[derive(Debug)]
+pub struct PlotterState<'a> {
+  pub current_point: Option<Vec2>,
+  pub selected_aperture: Option<(&'a str, &'a ApertureTemplatePrimitive)>,
+  pub interpolation: Option<&'a Interpolation>,
+  pub circular_direction: Option<&'a CircularDirection>,
+  pub polarity: &'a Polarity,
+  pub tolerance: &'a Tolerance,
+  pub is_in_region: bool,
+  pub path: Option<&'a RawPath>
+}
+
+type EmitHook = Box<dyn FnMut(&PlotterState, &Region)>;
+
 pub struct Plotter {
   unit: Option<Unit>,
   format: Option<FormatSpecification>,
+  tolerance: Tolerance,
+  // the largest absolute coordinate plotted so far
+  extent: f32,
   tools: HashMap<String, ApertureTemplatePrimitive>,
   previously_used_radial_interpolation: Option<Interpolation>,
   interpolation: Option<Interpolation>,
   circular_direction: Option<CircularDirection>,
   selected_aperture: Option<SelectedTool>,
-  collected_paths: Vec<Path>,
+  polarity: Polarity,
+  current_point: Option<Vec2>,
+  collected_regions: Vec<Region>,
+  region_issues: Vec<RegionIssue>,
   current_path: Option<RawPath>,
-  coords_accumulator: HashMap<Coordinate, f32>
+  current_span: Option<SourceSpan>,
+  contour_span: Option<SourceSpan>,
+  coords_accumulator: HashMap<Coordinate, f32>,
+  emit_hook: Option<EmitHook>
 }
 
 impl Plotter {
@@ -134,13 +214,21 @@ impl Plotter {
       interpolation: None,
       circular_direction: None,
       selected_aperture: None,
+      polarity: Polarity::Dark,
+      current_point: None,
       unit: None, 
       format: None, 
+      tolerance: Tolerance::default(),
+      extent: 0.0,
       tools: HashMap::new(),
-      collected_paths: Vec::new(),
+      collected_regions: Vec::new(),
+      region_issues: Vec::new(),
       // bounding_box: BoundingBox::default(),
       current_path: None,
-      coords_accumulator: HashMap::new()
+      current_span: None,
+      contour_span: None,
+      coords_accumulator: HashMap::new(),
+      emit_hook: None
 
     }
   }
@@ -149,7 +237,23 @@ impl Plotter {
     self.unit.replace(u);
   }
 
+  fn set_polarity(&mut self, p: Polarity) {
+    self.polarity = p;
+  }
+
+  fn move_current_point(&mut self, x: Option<f32>, y: Option<f32>) {
+    let current = self.current_point.unwrap_or_else(|| Vec2::new(0.0, 0.0));
+    let point = Vec2::new(x.unwrap_or(current.x), y.unwrap_or(current.y));
+    let extent = point.x.abs().max(point.y.abs());
+    if extent > self.extent {
+      self.extent = extent;
+      self.tolerance = self.tolerance.covering(extent);
+    }
+    self.current_point.replace(point);
+  }
+
   fn set_format(&mut self, f: FormatSpecification) {
+    self.tolerance = Tolerance::from_format(&f).covering(self.extent);
     self.format.replace(f);
   }
 
@@ -172,6 +276,7 @@ impl Plotter {
       self.tools.insert(key, template);
     }
     self.terminate_path();
+    self.contour_span = self.current_span.clone();
   }
 
   fn finish_contour(&mut self) {
@@ -179,6 +284,9 @@ impl Plotter {
     if let Some(SelectedTool::Aperture{ key, template }) = last_ap {
       self.tools.insert(key, template);
     }
+    if let Some(path) = self.current_path.as_mut() {
+      path.extend_span(self.current_span.clone());
+    }
     self.terminate_path();
   }
 
@@ -222,6 +330,14 @@ impl Plotter {
   }
 
 
+  /// Same as `consume`, but remembers where command came from,
+  /// so produced elements and regions can be traced back to the source.
+  pub fn consume_with_span(&mut self, command: GerberCommand, span: Option<SourceSpan>) {
+    self.current_span = span;
+    self.consume(command);
+    self.current_span = None;
+  }
+
   pub fn consume(&mut self, command: GerberCommand) {
     // println!("cmd: {:?}", command);
     match command {
@@ -236,6 +352,7 @@ impl Plotter {
       GerberCommand::Operation(op) => self.operation(op),
       GerberCommand::CounterClockWiseArc => self.set_circular_direction(CircularDirection::CCW),
       GerberCommand::ClockWiseArc => self.set_circular_direction(CircularDirection::CW),
+      GerberCommand::LevelPolarity(p) => self.set_polarity(p),
       _ => ()
     }
   }
@@ -259,8 +376,8 @@ impl Plotter {
         match template {
           R(r) => PathType::Rect(r.width, r.height),
           C(c) => PathType::Circle(c.diameter / 2.0),
-          P(p) => PathType::Circle(p.outer_diameter/2.0),
-          O(o) => PathType::Circle(o.width/2.0),
+          P(p) => PathType::Polygon(p.outer_diameter, p.number_of_vertices, p.rotation),
+          O(o) => PathType::Obround(o.width, o.height),
           M(_) => PathType::Circle(0.5)
         }
       },
@@ -276,17 +393,15 @@ impl Plotter {
     let ap = self.selected_aperture.as_ref();
     match ap {
       Some(_) => {
-        let pt = self.path_type();
-        let current_path = self.current_path.replace(
-          RawPath::start(pt)
-        );
-        if let Some(path) = &mut self.current_path {
-          path.push(RawPathElement::Start{x, y})
-        }
+        // finished path is emitted while the current point is still its end
+        self.emit();
+        self.move_current_point(x, y);
 
-        if let Some(path) =  current_path {
-          self.collected_paths.push(path.into());
-        };
+        let pt = self.path_type();
+        let mut path = RawPath::start(pt);
+        path.extend_span(self.contour_span.take());
+        path.push(RawPathElement::Start{x, y}, self.current_span.clone());
+        self.current_path.replace(path);
       },
       _ => panic!("Aperture is not selected")
     }
@@ -308,6 +423,7 @@ impl Plotter {
     if self.current_path.is_none() {
       self.start_new_path();
     }
+    self.move_current_point(x, y);
 
     let dir= self.circular_direction.clone().unwrap_or(CircularDirection::CW);
     let path_element = match self.interpolation {
@@ -318,11 +434,15 @@ impl Plotter {
     };
 
     if let Some(path) = self.current_path.as_mut() {
-      path.push(path_element);
+      path.push(path_element, self.current_span.clone());
     }
   }
 
 
+  pub fn get_tolerance(&self) -> Tolerance {
+    self.tolerance
+  }
+
   pub fn get_units(&mut self) -> Unit {
     match self.unit.take() {
       Some(u) => u,
@@ -331,22 +451,199 @@ impl Plotter {
   }
 
 
-  fn terminate_path(&mut self) {
-    if let Some(path) = self.current_path.take() {
-      if !path.elements.is_empty() {
-        self.collected_paths.push(path.into());
-      }
+  // called while the finished path is still current, so the hook sees it in the state
+  fn emit(&mut self) {
+    let (span, path) = match &self.current_path {
+      Some(raw) => (raw.span(), raw.to_path(&self.tolerance)),
+      None => return
     };
+    let mut issues = Vec::new();
+    if let PathType::Stroke = path.tp {
+      issues = validate_contour(&path, &self.tolerance);
+    }
+    let (mut regions, left_out) = Region::from_raw_region_with_issues(path, &self.tolerance, FillRule::NonZero);
+    for issue in left_out {
+      if !issues.contains(&issue) {
+        issues.push(issue);
+      }
+    }
+    self.region_issues.extend(issues);
+    for region in regions.iter_mut() {
+      region.source = span.clone();
+    }
+    if let Some(mut hook) = self.emit_hook.take() {
+      for region in regions.iter() {
+        hook(&self.get_state(), region);
+      }
+      self.emit_hook.replace(hook);
+    }
+    self.collected_regions.extend(regions);
   }
 
-  pub fn get_result(mut self) -> Vec<Region> {
-    self.terminate_path();
+  fn terminate_path(&mut self) {
+    if matches!(&self.current_path, Some(path) if !path.elements.is_empty()) {
+      self.emit();
+    }
+    self.current_path = None;
+  }
 
-    self.collected_paths.into_iter()
-      .map(|p| Region::from_raw_region(p))
-      .flatten()
-      .collect()
+  pub fn get_state(&self) -> PlotterState<'_> {
+    PlotterState {
+      current_point: self.current_point,
+      selected_aperture: match &self.selected_aperture {
+        Some(SelectedTool::Aperture{ key, template }) => Some((key.as_str(), template)),
+        _ => None
+      },
+      interpolation: self.interpolation.as_ref(),
+      circular_direction: self.circular_direction.as_ref(),
+      polarity: &self.polarity,
+      tolerance: &self.tolerance,
+      is_in_region: matches!(self.selected_aperture, Some(SelectedTool::Region)),
+      path: self.current_path.as_ref()
+    }
+  }
+
+  /// `hook` is called for every region as soon as it is emitted by the plotter.
+  pub fn set_emit_hook<F>(&mut self, hook: F) where F: FnMut(&PlotterState, &Region) + 'static {
+    self.emit_hook.replace(Box::new(hook));
+  }
+
+  pub fn get_result(self) -> Vec<Region> {
+    self.get_result_with_issues().0
+  }
+
+  /// Regions along with problems found in G36/G37 contours they are made of.
+  pub fn get_result_with_issues(mut self) -> (Vec<Region>, Vec<RegionIssue>) {
+    self.terminate_path();
+    (self.collected_regions, self.region_issues)
   }
 }
 
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::rc::Rc;
+  use std::cell::RefCell;
+  use crate::plotter::RegionIssueKind;
+
+  fn consume_all(plotter: &mut Plotter, src: &str) {
+    for cmd in GerberReader::new(src) {
+      match cmd.unwrap() {
+        Cmd::One(command) => plotter.consume(command),
+        Cmd::Many(commands) => for c in commands { plotter.consume(c) }
+      }
+    }
+  }
+
+  #[test]
+  fn state_after_consume() {
+    let mut plotter = Plotter::new();
+    consume_all(&mut plotter, "%FSLAX34Y34*%%MOIN*%%ADD10C,0.1*%%LPC*%D10*G01*X10000Y0D02*X10000Y5000D01*");
+    let state = plotter.get_state();
+    assert_eq!(state.current_point, Some(Vec2::new(1.0, 0.5)));
+    assert_eq!(state.selected_aperture.map(|(name, _)| name), Some("10"));
+    assert_eq!(state.interpolation, Some(&Interpolation::Linear));
+    assert_eq!(state.polarity, &Polarity::Clear);
+    assert_eq!(state.tolerance, &Tolerance::new(0.00005));
+    assert!(!state.is_in_region);
+    assert_eq!(state.path.map(|p| p.elements().len()), Some(2));
+  }
+
+  #[test]
+  fn tolerance_covers_plotted_coordinates() {
+    let mut plotter = Plotter::new();
+    consume_all(&mut plotter, "%FSLAX36Y36*%%MOMM*%%ADD10C,0.1*%D10*G01*X1000000Y0D02*X1000000Y5000000D01*");
+    assert_eq!(plotter.get_tolerance(), Tolerance::default());
+    consume_all(&mut plotter, "X600000000Y5000000D01*");
+    assert_eq!(plotter.get_tolerance(), Tolerance::default().covering(600.0));
+    consume_all(&mut plotter, "X0Y0D01*");
+    assert_eq!(plotter.get_tolerance(), Tolerance::default().covering(600.0));
+  }
+
+  #[test]
+  fn hook_is_called_per_region() {
+    let emitted = Rc::new(RefCell::new(Vec::new()));
+    let mut plotter = Plotter::new();
+    {
+      let emitted = emitted.clone();
+      plotter.set_emit_hook(move |state, _| {
+        emitted.borrow_mut().push((state.current_point, state.path.map(|p| p.elements().len())))
+      });
+    }
+    consume_all(&mut plotter, "%FSLAX34Y34*%%MOIN*%%ADD10C,0.1*%D10*G01*X0Y0D02*X10000Y0D01*X0Y10000D02*");
+    assert_eq!(emitted.borrow().len(), 1);
+    // hook sees the end of the emitted path, not the start of the next one
+    assert_eq!(emitted.borrow()[0], (Some(Vec2::new(1.0, 0.0)), Some(2)));
+    consume_all(&mut plotter, "X0Y0D01*");
+    assert_eq!(plotter.get_result().len(), 2);
+    assert_eq!(emitted.borrow().len(), 2);
+  }
+
+  fn consume_all_with_spans(plotter: &mut Plotter, src: &str) {
+    for (cmd, span) in GerberReader::new(src).with_spans() {
+      match cmd.unwrap() {
+        Cmd::One(command) => plotter.consume_with_span(command, span),
+        Cmd::Many(commands) => for c in commands { plotter.consume_with_span(c, span.clone()) }
+      }
+    }
+  }
+
+  #[test]
+  fn stroke_sources() {
+    let src = "%FSLAX34Y34*%\n%MOIN*%\n%ADD10C,0.1*%\nD10*\nG01*\nX0Y0D02*\nX10000Y0D01*\n";
+    let mut plotter = Plotter::new();
+    consume_all_with_spans(&mut plotter, src);
+    let regions = plotter.get_result();
+    assert_eq!(regions.len(), 1);
+    let span = regions[0].source.clone().unwrap();
+    assert_eq!(span.line, 6);
+    assert_eq!(&src[span.start..span.end], "X0Y0D02*\nX10000Y0D01*");
+    for element in regions[0].paths.data.elements.iter() {
+      assert_eq!(element.get_source().map(|s| s.line), Some(7));
+    }
+  }
 
+  #[test]
+  fn contour_sources() {
+    let src = "%FSLAX34Y34*%\n%MOIN*%\nG01*\nG36*\nX0Y0D02*\nX10000Y0D01*\nX0Y10000D01*\nX0Y0D01*\nG37*\n";
+    let mut plotter = Plotter::new();
+    consume_all_with_spans(&mut plotter, src);
+    let regions = plotter.get_result();
+    assert_eq!(regions.len(), 1);
+    let span = regions[0].source.clone().unwrap();
+    assert_eq!(span.line, 4);
+    assert!(src[span.start..span.end].starts_with("G36*"));
+    assert!(src[span.start..span.end].ends_with("G37*"));
+    let lines: Vec<usize> = regions[0].paths.data.elements.iter()
+      .map(|element| element.get_source().unwrap().line)
+      .collect();
+    assert_eq!(lines.len(), 3);
+    for line in [6, 7, 8] {
+      assert!(lines.contains(&line));
+    }
+  }
+
+  #[test]
+  fn contour_issues() {
+    let src = "%FSLAX34Y34*%\n%MOIN*%\nG01*\nG36*\nX0Y0D02*\nX10000Y10000D01*\nX10000Y0D01*\nX0Y10000D01*\nX0Y0D01*\nG37*\n";
+    let mut plotter = Plotter::new();
+    consume_all_with_spans(&mut plotter, src);
+    let (_, issues) = plotter.get_result_with_issues();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RegionIssueKind::SelfIntersection);
+    assert!((issues[0].location - Vec2::new(0.5, 0.5)).magnitude() < 1e-4);
+    assert_eq!(issues[0].source.as_ref().map(|s| s.line), Some(8));
+  }
+
+  #[test]
+  fn contour_tail_left_out_of_region() {
+    let src = "%FSLAX34Y34*%\n%MOIN*%\nG01*\nG36*\nX0Y0D02*\nX10000Y0D01*\nX10000Y10000D01*\nX0Y10000D01*\nX0Y0D01*\nX-5000Y-5000D01*\nG37*\n";
+    let mut plotter = Plotter::new();
+    consume_all_with_spans(&mut plotter, src);
+    let (_, issues) = plotter.get_result_with_issues();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RegionIssueKind::Unclosed);
+    assert!((issues[0].location - Vec2::new(-0.5, -0.5)).magnitude() < 1e-4);
+    assert_eq!(issues[0].source.as_ref().map(|s| s.line), Some(10));
+  }
+}
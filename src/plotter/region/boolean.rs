@@ -0,0 +1,455 @@
+extern crate nalgebra as na;
+use na::{ Rotation2, Vector2 };
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::parser::Polarity;
+use super::Region;
+use super::super::{
+  bounds,
+  intersection_points,
+  tr,
+  AlgebraicPathElement,
+  BoundingBox,
+  Bvh,
+  Path,
+  StrokePathElement,
+  Tolerance,
+  Tree,
+};
+use super::super::bvh::{ overlaps, union_box };
+use super::super::path::subtended_angle;
+
+type Vec2 = Vector2<f32>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BooleanOp {
+  Union,
+  Intersection,
+  Difference,
+  Xor
+}
+
+impl BooleanOp {
+  fn apply(self, in_a: bool, in_b: bool) -> bool {
+    match self {
+      BooleanOp::Union => in_a || in_b,
+      BooleanOp::Intersection => in_a && in_b,
+      BooleanOp::Difference => in_a && !in_b,
+      BooleanOp::Xor => in_a != in_b
+    }
+  }
+}
+
+// Every edge of both operands is split in all crossings and touches, then
+// kept if the result is filled on exactly one side of it. Kept edges are
+// oriented with the filled side on the left and chained into contours:
+// counterclockwise ones become outlines and clockwise ones - their holes.
+pub fn boolean(op: BooleanOp, a: &[Region], b: &[Region], tolerance: &Tolerance) -> Vec<Region> {
+  let (cover_a, cover_b) = (Coverage::new(a, tolerance), Coverage::new(b, tolerance));
+  let mut kept: Vec<Box<dyn StrokePathElement>> = Vec::new();
+  for edge in split_edges(a, b, tolerance).into_iter() {
+    let (left, right) = side_points(edge.as_ref(), tolerance);
+    let fill_left = op.apply(cover_a.contains(&left), cover_b.contains(&left));
+    let fill_right = op.apply(cover_a.contains(&right), cover_b.contains(&right));
+    match (fill_left, fill_right) {
+      (true, false) => kept.push(edge),
+      (false, true) => kept.push(edge.reversed()),
+      _ => {}
+    }
+  }
+  let kept = remove_duplicates(kept, tolerance);
+  compose(chain_contours(kept, tolerance), tolerance)
+}
+
+fn region_contours(regions: &[Region]) -> Vec<&Path> {
+  regions.iter().flat_map(|region| region.contours()).collect()
+}
+
+const RUN_SIZE: usize = 8;
+
+struct Run {
+  bounds: BoundingBox,
+  start: usize,
+  end: usize,
+  halves: Option<(usize, usize)>
+}
+
+// Consecutive elements of a contour grouped into a binary hierarchy of runs.
+// A point outside the box of a run sees it under the angle between its ends,
+// so winding number is summed up only from the elements around the point.
+struct ContourIndex<'a> {
+  elements: &'a [Box<dyn StrokePathElement>],
+  runs: Vec<Run>,
+  tolerance: Tolerance
+}
+
+impl<'a> ContourIndex<'a> {
+  fn new(path: &'a Path, tolerance: &Tolerance) -> Self {
+    let boxes: Vec<BoundingBox> = path.elements.iter()
+      .map(|element| bounds(element.as_ref(), tolerance))
+      .collect();
+    let mut index = ContourIndex { elements: &path.elements, runs: Vec::new(), tolerance: *tolerance };
+    if !boxes.is_empty() {
+      index.build(&boxes, 0, boxes.len());
+    }
+    index
+  }
+
+  fn build(&mut self, boxes: &[BoundingBox], start: usize, end: usize) -> usize {
+    let run = self.runs.len();
+    let bounds = boxes[start + 1..end].iter().fold(boxes[start], |acc, b| union_box(&acc, b));
+    self.runs.push(Run { bounds, start, end, halves: None });
+    if end - start > RUN_SIZE {
+      let middle = (start + end) / 2;
+      let left = self.build(boxes, start, middle);
+      let right = self.build(boxes, middle, end);
+      self.runs[run].halves = Some((left, right));
+    }
+    run
+  }
+
+  fn winding_number(&self, point: &Vec2) -> i32 {
+    let mut angle = 0.0;
+    let mut stack = if self.runs.is_empty() { Vec::new() } else { vec!(0) };
+    while let Some(run) = stack.pop() {
+      let Run { bounds, start, end, halves } = &self.runs[run];
+      if !overlaps(bounds, &(*point, *point)) {
+        let from = self.elements[*start].get_start_point() - point;
+        let to = self.elements[*end - 1].get_end_point() - point;
+        angle += Rotation2::rotation_between(&from, &to).angle();
+        continue;
+      }
+      match halves {
+        Some((left, right)) => {
+          stack.push(*left);
+          stack.push(*right);
+        },
+        None => angle += self.elements[*start..*end].iter()
+          .map(|element| subtended_angle(element.as_ref(), point, &self.tolerance))
+          .sum::<f32>()
+      }
+    }
+    (angle / (2.0 * PI)).round() as i32
+  }
+}
+
+// Regions of one operand indexed by their bounding boxes, so a point is
+// tested only against the regions around it.
+struct Coverage<'a> {
+  contours: Vec<Vec<ContourIndex<'a>>>,
+  index: Bvh
+}
+
+impl<'a> Coverage<'a> {
+  fn new(regions: &'a [Region], tolerance: &Tolerance) -> Self {
+    let contours: Vec<Vec<ContourIndex<'a>>> = regions.iter()
+      .map(|region| region.contours().into_iter().map(|path| ContourIndex::new(path, tolerance)).collect())
+      .collect();
+    let boxes: Vec<BoundingBox> = contours.iter()
+      .map(|paths| {
+        paths.iter()
+          .filter_map(|path| path.runs.first().map(|run| run.bounds))
+          .fold(None, |acc: Option<BoundingBox>, b| Some(acc.map(|acc| union_box(&acc, &b)).unwrap_or(b)))
+          // region without elements covers nothing and is never found
+          .unwrap_or((Vec2::new(f32::INFINITY, f32::INFINITY), Vec2::new(f32::INFINITY, f32::INFINITY)))
+      })
+      .collect();
+    Coverage { contours, index: Bvh::new(&boxes) }
+  }
+
+  // Contours are nested, so the point is inside when it is surrounded by odd
+  // number of them. Winding of each contour is not summed up, as stroke
+  // outlines may overlap themselves.
+  fn contains(&self, point: &Vec2) -> bool {
+    self.index.in_window(&(*point, *point)).into_iter()
+      .any(|ix| {
+        let around = self.contours[ix].iter()
+          .filter(|path| path.winding_number(point) != 0)
+          .count();
+        around % 2 != 0
+      })
+  }
+}
+
+// Edges, which start within tolerance from the point.
+fn starting_near(
+  starts: &HashMap<(i64, i64), Vec<usize>>,
+  point: &Vec2,
+  tolerance: &Tolerance
+) -> Vec<usize> {
+  tolerance.nearby_keys(point).iter()
+    .filter_map(|key| starts.get(key))
+    .flat_map(|edges| edges.iter().cloned())
+    .collect()
+}
+
+fn is_inner_point(element: &dyn StrokePathElement, point: &Vec2, tolerance: &Tolerance) -> bool {
+  let is_end = tolerance.is_same_point(point, &element.get_start_point())
+    || tolerance.is_same_point(point, &element.get_end_point());
+  if is_end {
+    return false;
+  }
+  let distance = match element.algebraic() {
+    AlgebraicPathElement::Line(line) => {
+      let dir = (line.to - line.from).normalize();
+      let v = point - line.from;
+      (v.x * dir.y - v.y * dir.x).abs()
+    },
+    AlgebraicPathElement::Arc(arc) => ((point - arc.center).magnitude() - arc.get_radius()).abs()
+  };
+  let t = element.parameter_of(point);
+  tolerance.is_zero(distance) && t > 0.0 && t < 1.0
+}
+
+fn split_edges(a: &[Region], b: &[Region], tolerance: &Tolerance) -> Vec<Box<dyn StrokePathElement>> {
+  let elements: Vec<&dyn StrokePathElement> = region_contours(a).into_iter()
+    .chain(region_contours(b))
+    .flat_map(|path| path.elements.iter().map(|el| el.as_ref()))
+    .collect();
+
+  let split_points = intersection_points(&elements, tolerance);
+
+  elements.into_iter()
+    .zip(split_points)
+    .flat_map(|(element, points)| {
+      let points: Vec<Vec2> = points.into_iter()
+        .filter(|p| is_inner_point(element, p, tolerance))
+        .collect();
+      if points.is_empty() {
+        vec!(element.create_forward_with(element.get_start_point(), element.get_end_point()))
+      } else {
+        element.split_by_points(&points).into_iter()
+          .filter(|piece| !tolerance.is_same_point(&piece.get_end_point(), &piece.get_start_point()))
+          .collect()
+      }
+    })
+    .filter(|piece| !tolerance.is_zero(piece.length()))
+    .collect()
+}
+
+fn side_points(element: &dyn StrokePathElement, tolerance: &Tolerance) -> (Vec2, Vec2) {
+  let offset = (element.length() * 0.01).max(tolerance.distance).min(1e-3);
+  let center = element.get_central_point();
+  let normal = element.get_normal_in_central_point().normalize() * offset;
+  (center + normal, center - normal)
+}
+
+fn is_same_edge(one: &dyn StrokePathElement, two: &dyn StrokePathElement, tolerance: &Tolerance) -> bool {
+  let same_ends = tolerance.is_same_point(&one.get_start_point(), &two.get_start_point())
+    && tolerance.is_same_point(&one.get_end_point(), &two.get_end_point());
+  same_ends && match (one.algebraic(), two.algebraic()) {
+    (AlgebraicPathElement::Line(_), AlgebraicPathElement::Line(_)) => true,
+    (AlgebraicPathElement::Arc(one), AlgebraicPathElement::Arc(two)) =>
+      one.direction == two.direction && tolerance.is_same_point(&one.center, &two.center),
+    _ => false
+  }
+}
+
+fn remove_duplicates(edges: Vec<Box<dyn StrokePathElement>>, tolerance: &Tolerance) -> Vec<Box<dyn StrokePathElement>> {
+  let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
+  let mut starts: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+  for edge in edges.into_iter() {
+    let start = edge.get_start_point();
+    let is_duplicate = starting_near(&starts, &start, tolerance).into_iter()
+      .any(|ix| is_same_edge(result[ix].as_ref(), edge.as_ref(), tolerance));
+    if !is_duplicate {
+      starts.entry(tolerance.grid_key(&start)).or_default().push(result.len());
+      result.push(edge);
+    }
+  }
+  result
+}
+
+fn turn_angle(direction: &Vec2, next: &dyn StrokePathElement) -> f32 {
+  Rotation2::rotation_between(direction, &next.get_direction_in_start_point()).angle()
+}
+
+// In vertices, where several contours meet, the sharpest left turn is taken,
+// so touching contours are not merged into one.
+fn chain_contours(edges: Vec<Box<dyn StrokePathElement>>, tolerance: &Tolerance) -> Vec<Path> {
+  let mut starts: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+  for (ix, edge) in edges.iter().enumerate() {
+    starts.entry(tolerance.grid_key(&edge.get_start_point())).or_default().push(ix);
+  }
+  // chained edges are taken out of their slots
+  let mut edges: Vec<Option<Box<dyn StrokePathElement>>> = edges.into_iter().map(Some).collect();
+  let mut contours = Vec::new();
+  for first in 0..edges.len() {
+    let first = match edges[first].take() {
+      Some(edge) => edge,
+      None => continue
+    };
+    let start = first.get_start_point();
+    let mut elements = vec!(first);
+    let is_closed = loop {
+      let last = elements.last().unwrap();
+      let end = last.get_end_point();
+      if tolerance.is_same_point(&end, &start) {
+        break true;
+      }
+      let direction = last.get_direction_in_end_point();
+      let next = starting_near(&starts, &end, tolerance).into_iter()
+        .filter_map(|ix| edges[ix].as_ref().map(|edge| (ix, edge)))
+        .filter(|(_, edge)| tolerance.is_same_point(&edge.get_start_point(), &end))
+        .max_by(|(ix_one, one), (ix_two, two)| {
+          turn_angle(&direction, one.as_ref())
+            .partial_cmp(&turn_angle(&direction, two.as_ref()))
+            .unwrap()
+            // of equal turns the latest edge is taken
+            .then(ix_one.cmp(ix_two))
+        })
+        .map(|(ix, _)| ix);
+      match next.and_then(|ix| edges[ix].take()) {
+        Some(edge) => elements.push(edge),
+        None => break false
+      }
+    };
+    if is_closed {
+      contours.push(Path::stroke(elements));
+    }
+  }
+  contours
+}
+
+fn compose(contours: Vec<Path>, tolerance: &Tolerance) -> Vec<Region> {
+  let (outlines, holes): (Vec<_>, Vec<_>) = contours.into_iter()
+    .map(|path| (path.signed_area(), path))
+    .partition(|(area, _)| *area > 0.0);
+
+  let mut trees: Vec<(f32, Tree<Path>)> = outlines.into_iter()
+    .map(|(area, path)| (area, tr(path)))
+    .collect();
+
+  for (_, hole) in holes.into_iter() {
+    let probe = hole.elements[0].get_central_point();
+    let parent = trees.iter_mut()
+      .filter(|(_, tree)| tree.data.is_point_inside(&probe, tolerance))
+      .min_by(|(one, _), (two, _)| one.partial_cmp(two).unwrap());
+    if let Some((_, tree)) = parent {
+      tree.push(hole);
+    }
+  }
+
+  trees.into_iter()
+    .map(|(_, tree)| Region::new(Polarity::Dark, tree))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::Line;
+  use crate::plotter::fixtures::{ circle, square };
+  use std::f32::consts::PI;
+
+  fn area(regions: &[Region]) -> f32 {
+    region_contours(regions).into_iter().map(|path| path.signed_area()).sum()
+  }
+
+  fn elements_count(region: &Region) -> usize {
+    region.contours().into_iter().map(|path| path.elements.len()).sum()
+  }
+
+  #[test]
+  fn overlapping_squares() {
+    let a = vec!(square(0.0, 0.0, 2.0));
+    let b = vec!(square(1.0, 1.0, 2.0));
+
+    let result = boolean(BooleanOp::Union, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert_eq!(elements_count(&result[0]), 8);
+    assert!((area(&result) - 7.0).abs() < 1e-4);
+
+    let result = boolean(BooleanOp::Intersection, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert_eq!(elements_count(&result[0]), 4);
+    assert!((area(&result) - 1.0).abs() < 1e-4);
+
+    let result = boolean(BooleanOp::Difference, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert_eq!(elements_count(&result[0]), 6);
+    assert!((area(&result) - 3.0).abs() < 1e-4);
+
+    let result = boolean(BooleanOp::Xor, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 2);
+    assert!((area(&result) - 6.0).abs() < 1e-4);
+  }
+
+  #[test]
+  fn squares_with_shared_edge() {
+    let a = vec!(square(0.0, 0.0, 1.0));
+    let b = vec!(square(1.0, 0.0, 1.0));
+
+    let result = boolean(BooleanOp::Union, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert_eq!(elements_count(&result[0]), 6);
+    assert!((area(&result) - 2.0).abs() < 1e-4);
+
+    assert!(boolean(BooleanOp::Intersection, &a, &b, &Tolerance::default()).is_empty());
+  }
+
+  #[test]
+  fn hole_keeps_arc() {
+    let a = vec!(square(0.0, 0.0, 2.0));
+    let b = vec!(circle(1.0, 1.0, 0.5));
+
+    let result = boolean(BooleanOp::Difference, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    let holes = result[0].paths.children();
+    assert_eq!(holes.len(), 1);
+    assert!(holes[0].data.elements.iter().all(|el| matches!(el.algebraic(), AlgebraicPathElement::Arc(_))));
+    assert!((area(&result) - (4.0 - PI * 0.25)).abs() < 1e-4);
+  }
+
+  #[test]
+  fn union_of_circles() {
+    let a = vec!(circle(0.0, 0.0, 1.0));
+    let b = vec!(circle(1.0, 0.0, 1.0));
+
+    let result = boolean(BooleanOp::Union, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    // second circle keeps its starting point as a vertex
+    assert_eq!(elements_count(&result[0]), 3);
+    assert!(result[0].paths.data.elements.iter().all(|el| matches!(el.algebraic(), AlgebraicPathElement::Arc(_))));
+    let lens = 2.0 * (0.5f32).acos() - 0.5 * 3.0f32.sqrt();
+    assert!((area(&result) - (2.0 * PI - lens)).abs() < 1e-4);
+
+    let result = boolean(BooleanOp::Intersection, &a, &b, &Tolerance::default());
+    assert!((area(&result) - lens).abs() < 1e-4);
+  }
+
+  #[test]
+  fn contour_index_winds_as_path() {
+    // star with 100 rays, so most of the runs are far from any probe
+    let points: Vec<Vec2> = (0..200)
+      .map(|ix| {
+        let angle = ix as f32 * PI / 100.0;
+        let radius = if ix % 2 == 0 { 2.0 } else { 1.0 };
+        radius * Vec2::new(angle.cos(), angle.sin())
+      })
+      .collect();
+    let elements: Vec<Box<dyn StrokePathElement>> = (0..points.len())
+      .map(|ix| {
+        let line: Box<dyn StrokePathElement> = Box::new(Line::new(points[(ix + 1) % points.len()], points[ix]));
+        line
+      })
+      .collect();
+    let path = Path::stroke(elements);
+    let index = ContourIndex::new(&path, &Tolerance::default());
+    for x in -12..12 {
+      for y in -12..12 {
+        let point = Vec2::new(x as f32 * 0.19 + 0.001, y as f32 * 0.19 + 0.002);
+        assert_eq!(index.winding_number(&point), path.winding_number(&point, &Tolerance::default()));
+      }
+    }
+  }
+
+  #[test]
+  fn many_overlapping_tracks() {
+    let a: Vec<Region> = (0..40).map(|ix| square(ix as f32 * 0.5, 0.0, 1.0)).collect();
+    let b: Vec<Region> = (0..40).map(|ix| square(ix as f32 * 0.5, 0.5, 1.0)).collect();
+    let result = boolean(BooleanOp::Union, &a, &b, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert!((area(&result) - 20.5 * 1.5).abs() < 1e-3);
+  }
+}
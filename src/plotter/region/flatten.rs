@@ -0,0 +1,72 @@
+use crate::parser::Polarity;
+use super::{ boolean, BooleanOp, Region };
+use super::super::Tolerance;
+
+// Regions are applied in the order they were plotted: dark ones are added
+// to the image, clear ones are cut out of everything plotted before them.
+// Result is a set of disjoint islands, each of them with its holes only.
+pub fn flatten(regions: &[Region], tolerance: &Tolerance) -> Vec<Region> {
+  let mut image: Vec<Region> = Vec::new();
+  let mut rest = regions;
+  while let Some(first) = rest.first() {
+    let polarity = &first.starting_polirity;
+    let run = rest.iter()
+      .take_while(|region| &region.starting_polirity == polarity)
+      .count();
+    let (batch, tail) = rest.split_at(run);
+    image = match polarity {
+      Polarity::Dark => boolean(BooleanOp::Union, &image, batch, tolerance),
+      Polarity::Clear => boolean(BooleanOp::Difference, &image, batch, tolerance)
+    };
+    rest = tail;
+  }
+  image
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::tr;
+  use crate::plotter::fixtures::square_path;
+
+  fn square(x: f32, y: f32, size: f32, polarity: Polarity) -> Region {
+    Region::new(polarity, tr(square_path(x, y, size)))
+  }
+
+  #[test]
+  fn overlapping_dark_regions_merge() {
+    let regions = vec!(
+      square(0.0, 0.0, 2.0, Polarity::Dark),
+      square(1.0, 1.0, 2.0, Polarity::Dark),
+      square(5.0, 5.0, 1.0, Polarity::Dark),
+    );
+    let result = flatten(&regions, &Tolerance::default());
+    assert_eq!(result.len(), 2);
+    assert!(result.iter().all(|region| region.paths.is_leaf()));
+  }
+
+  #[test]
+  fn clear_regions_make_holes() {
+    let regions = vec!(
+      square(0.0, 0.0, 4.0, Polarity::Dark),
+      square(1.0, 1.0, 2.0, Polarity::Clear),
+      square(1.5, 1.5, 1.0, Polarity::Dark),
+    );
+    let result = flatten(&regions, &Tolerance::default());
+    assert_eq!(result.len(), 2);
+    let holes: Vec<usize> = result.iter()
+      .map(|region| region.paths.children().len())
+      .collect();
+    assert!(holes.contains(&1));
+    assert!(holes.contains(&0));
+  }
+
+  #[test]
+  fn clear_region_does_not_affect_later_ones() {
+    let regions = vec!(
+      square(0.0, 0.0, 1.0, Polarity::Clear),
+      square(0.0, 0.0, 1.0, Polarity::Dark),
+    );
+    assert_eq!(flatten(&regions, &Tolerance::default()).len(), 1);
+  }
+}
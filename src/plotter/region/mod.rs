@@ -1,8 +1,16 @@
 mod stroke_path;
 mod split_regions;
 mod region_impl;
+mod boolean;
+mod flatten;
+mod offset;
+mod validate;
 
 pub use stroke_path::*;
 pub use split_regions::*;
 pub use region_impl::*;
+pub use boolean::*;
+pub use flatten::*;
+pub use offset::*;
+pub use validate::*;
 
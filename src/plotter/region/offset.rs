@@ -0,0 +1,160 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use crate::parser::Polarity;
+use super::{ boolean, BooleanOp, Region };
+use super::super::{
+  tr,
+  AlgebraicPathElement,
+  Arc,
+  CircularDirection,
+  Line,
+  Path,
+  StrokePathElement,
+  Tolerance,
+  Transform,
+};
+
+type Vec2 = Vector2<f32>;
+
+fn contour(elements: Vec<Box<dyn StrokePathElement>>) -> Region {
+  Region::new(Polarity::Dark, tr(Path::stroke(elements)))
+}
+
+fn disk(center: Vec2, radius: f32) -> Region {
+  let point = center + Vec2::new(radius, 0.0);
+  contour(vec!(Box::new(Arc::new_with_fixed_center(point, point, center, CircularDirection::CCW))))
+}
+
+// Points not further than `distance` from the line, except the round ends.
+fn line_band(line: &Line, distance: f32) -> Region {
+  let normal = line.get_normal_in_central_point() * distance;
+  let corners = [line.from - normal, line.to - normal, line.to + normal, line.from + normal];
+  contour((0..4)
+    .map(|ix| {
+      let side: Box<dyn StrokePathElement> = Box::new(Line::new(corners[(ix + 1) % 4], corners[ix]));
+      side
+    })
+    .collect())
+}
+
+// Points not further than `distance` from the arc, except the round ends.
+fn arc_band(arc: &Arc, distance: f32, tolerance: &Tolerance) -> Region {
+  let (from, to) = match arc.direction {
+    CircularDirection::CCW => (arc.from, arc.to),
+    CircularDirection::CW => (arc.to, arc.from)
+  };
+  let center = arc.center;
+  let radius = arc.get_radius();
+  let outer = |point: Vec2| center + (point - center) * ((radius + distance) / radius);
+  let inner = |point: Vec2| center + (point - center) * ((radius - distance) / radius);
+  let is_full = tolerance.is_same_point(&from, &to);
+  let has_hole = radius - distance > tolerance.distance;
+
+  let outer_arc: Box<dyn StrokePathElement> = Box::new(
+    Arc::new_with_fixed_center(outer(to), outer(from), center, CircularDirection::CCW)
+  );
+  if is_full {
+    let mut paths = tr(Path::stroke(vec!(outer_arc)));
+    if has_hole {
+      paths.push(Path::stroke(vec!(
+        Box::new(Arc::new_with_fixed_center(inner(from), inner(from), center, CircularDirection::CW))
+      )));
+    }
+    return Region::new(Polarity::Dark, paths);
+  }
+  let (start, end) = if has_hole { (inner(from), inner(to)) } else { (center, center) };
+  let mut elements = vec!(
+    Box::new(Line::new(outer(from), start)) as Box<dyn StrokePathElement>,
+    outer_arc,
+    Box::new(Line::new(end, outer(to)))
+  );
+  if has_hole {
+    elements.push(Box::new(Arc::new_with_fixed_center(start, end, center, CircularDirection::CW)));
+  }
+  contour(elements)
+}
+
+// Regions grown by positive distance or shrunk by negative one. Growing adds
+// everything within the distance from the boundary, shrinking cuts it out,
+// so convex corners get round when growing and concave ones when shrinking.
+pub fn offset(regions: &[Region], distance: f32, tolerance: &Tolerance) -> Vec<Region> {
+  if tolerance.is_zero(distance) {
+    return regions.iter().map(|region| region.transformed(&Transform::identity())).collect();
+  }
+  let width = distance.abs();
+  let mut band: Vec<Region> = Vec::new();
+  for path in regions.iter().flat_map(|region| region.contours()) {
+    for element in path.elements.iter() {
+      if tolerance.is_zero(element.length()) {
+        continue;
+      }
+      band.push(disk(element.get_start_point(), width));
+      band.push(match element.algebraic() {
+        AlgebraicPathElement::Line(line) => line_band(&line, width),
+        AlgebraicPathElement::Arc(arc) => arc_band(&arc, width, tolerance)
+      });
+    }
+  }
+  let op = if distance > 0.0 { BooleanOp::Union } else { BooleanOp::Difference };
+  boolean(op, regions, &band, tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::fixtures::square;
+  use std::f32::consts::PI;
+
+  fn arcs_count(regions: &[Region]) -> usize {
+    regions.iter()
+      .flat_map(|region| region.contours())
+      .flat_map(|path| path.elements.iter())
+      .filter(|el| match el.algebraic() {
+        AlgebraicPathElement::Arc(_) => true,
+        AlgebraicPathElement::Line(_) => false
+      })
+      .count()
+  }
+
+  #[test]
+  fn grown_square_gets_round_corners() {
+    let result = offset(&[square(0.0, 0.0, 2.0)], 0.5, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert_eq!(arcs_count(&result), 4);
+    assert!((result[0].area() - (4.0 + 4.0 + 0.25 * PI)).abs() < 1e-3);
+  }
+
+  #[test]
+  fn shrunk_square_keeps_sharp_corners() {
+    let result = offset(&[square(0.0, 0.0, 2.0)], -0.5, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert_eq!(arcs_count(&result), 0);
+    assert!((result[0].area() - 1.0).abs() < 1e-3);
+  }
+
+  #[test]
+  fn shrunk_away_island_is_dropped() {
+    let regions = vec!(square(0.0, 0.0, 2.0), disk(Vec2::new(10.0, 0.0), 3.0));
+    let result = offset(&regions, -1.5, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    assert!((result[0].area() - PI * 1.5 * 1.5).abs() < 1e-3);
+    assert_eq!(arcs_count(&result), 1);
+  }
+
+  #[test]
+  fn hole_grows_when_region_shrinks() {
+    let mut paths = tr(Path::stroke(square(0.0, 0.0, 4.0).paths.data.elements.iter()
+      .map(|el| el.translated(&Vec2::new(-2.0, -2.0)))
+      .collect()));
+    let center = Vec2::new(0.0, 0.0);
+    let point = Vec2::new(1.0, 0.0);
+    paths.push(Path::stroke(vec!(
+      Box::new(Arc::new_with_fixed_center(point, point, center, CircularDirection::CW))
+    )));
+    let region = Region::new(Polarity::Dark, paths);
+    let result = offset(&[region], -0.25, &Tolerance::default());
+    assert_eq!(result.len(), 1);
+    let expected = 3.5 * 3.5 - PI * 1.25 * 1.25;
+    assert!((result[0].area() - expected).abs() < 1e-3);
+  }
+}
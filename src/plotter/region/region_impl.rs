@@ -1,27 +1,477 @@
-use super::super::{ PathType, Path };
-use crate::parser::Polarity;
-use super::{ split_region_paths, to_stroke_around_path };
-use super::super::Tree;
+use super::super::{
+  convex_hull,
+  distance,
+  min_area_rectangle,
+  AlgebraicPathElement,
+  CircularDirection,
+  Line,
+  OrientedRectangle,
+  PathType,
+  Path,
+  StrokePathElement,
+  Tolerance,
+  Transform,
+  Vec2,
+};
+use crate::parser::{ Polarity, SourceSpan };
+use super::{ split_region_paths_with_issues, to_stroke_around_path, RegionIssue, boolean, offset, BooleanOp };
+use super::super::{ tr, Tree };
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FillRule {
+  EvenOdd,
+  NonZero
+}
+
+impl FillRule {
+  pub fn is_filled(self, winding_number: i32) -> bool {
+    match self {
+      FillRule::EvenOdd => winding_number % 2 != 0,
+      FillRule::NonZero => winding_number != 0
+    }
+  }
+}
+
+// Which side of exact contour polygons made of arcs are placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlattenSide {
+  // Vertices are on the arcs.
+  Chords,
+  // Polygon covers no more than the region.
+  Inside,
+  // Polygon covers all of the region.
+  Outside
+}
+
+impl FlattenSide {
+  // Dark regions are never shrunk and clear ones are never grown.
+  pub fn conservative(polarity: &Polarity) -> Self {
+    match polarity {
+      Polarity::Dark => FlattenSide::Outside,
+      Polarity::Clear => FlattenSide::Inside
+    }
+  }
+}
+
+pub struct RegionContour<'a> {
+  pub path: &'a Path,
+  pub depth: usize,
+  pub polarity: Polarity
+}
 
 pub struct Region {
   pub starting_polirity: Polarity,
-  pub paths: Tree<Path>
+  pub paths: Tree<Path>,
+  pub source: Option<SourceSpan>
 }
 
 impl Region {
-  pub fn from_raw_region(path: Path) -> Vec<Self> {
-    println!("read reg");
-    match path.tp {
-      PathType::Stroke => split_region_paths(path),
-      _ => to_stroke_around_path(path)
+  // Parts of a contour overlapping each other are filled as Gerber requires,
+  // that is by nonzero rule.
+  pub fn from_raw_region(path: Path, tolerance: &Tolerance) -> Vec<Self> {
+    Region::from_raw_region_with_fill_rule(path, tolerance, FillRule::NonZero)
+  }
+
+  pub fn from_raw_region_with_fill_rule(path: Path, tolerance: &Tolerance, fill_rule: FillRule) -> Vec<Self> {
+    Region::from_raw_region_with_issues(path, tolerance, fill_rule).0
+  }
+
+  // Regions along with parts of the contour, which are left out of them.
+  pub fn from_raw_region_with_issues(path: Path, tolerance: &Tolerance, fill_rule: FillRule) -> (Vec<Self>, Vec<RegionIssue>) {
+    let (mut regions, issues) = match path.tp {
+      PathType::Stroke => split_region_paths_with_issues(path, tolerance, fill_rule),
+      _ => (to_stroke_around_path(path, tolerance), Vec::new())
+    };
+    for region in regions.iter_mut() {
+      region.normalize_orientation();
+    }
+    (regions, issues)
+  }
+
+  pub fn simplified(&self, tolerance: &Tolerance) -> Region {
+    Region {
+      starting_polirity: self.starting_polirity.clone(),
+      paths: self.paths.map(&|path: &Path| path.simplified(tolerance)),
+      source: self.source.clone()
     }
   }
 
+  pub fn fit_arcs(&self, tolerance: &Tolerance, max_chord_error: f32) -> Region {
+    Region {
+      starting_polirity: self.starting_polirity.clone(),
+      paths: self.paths.map(&|path: &Path| path.fit_arcs(tolerance, max_chord_error)),
+      source: self.source.clone()
+    }
+  }
+
+  // Outlines and islands go counterclockwise, holes go clockwise.
+  pub fn normalize_orientation(&mut self) {
+    fn normalize(tree: &mut Tree<Path>, depth: usize) {
+      if tree.data.is_ccw() != depth.is_multiple_of(2) {
+        tree.data = tree.data.reversed();
+      }
+      for child in tree.forest_mut().iter_mut() {
+        normalize(child, depth + 1);
+      }
+    }
+    normalize(&mut self.paths, 0);
+  }
+
   pub fn new(starting_polirity: Polarity, paths: Tree<Path>) -> Self {
     Region {
       starting_polirity, 
-      paths
+      paths,
+      source: None
+    }
+  }
+
+  // Every contour with its nesting depth and the polarity it paints with:
+  // outlines and islands at even depths, holes at odd ones.
+  pub fn walk(&self) -> Vec<RegionContour<'_>> {
+    self.paths.walk()
+      .map(|(depth, path)| RegionContour {
+        path,
+        depth,
+        polarity: if depth.is_multiple_of(2) {
+          self.starting_polirity.clone()
+        } else {
+          self.starting_polirity.switch()
+        }
+      })
+      .collect()
+  }
+
+  pub fn contours(&self) -> Vec<&Path> {
+    self.paths.walk().map(|(_, path)| path).collect()
+  }
+
+  pub fn outer_contours(&self) -> Vec<&Path> {
+    self.paths.walk()
+      .filter(|(depth, _)| depth.is_multiple_of(2))
+      .map(|(_, path)| path)
+      .collect()
+  }
+
+  pub fn holes(&self) -> Vec<&Path> {
+    self.paths.walk()
+      .filter(|(depth, _)| !depth.is_multiple_of(2))
+      .map(|(_, path)| path)
+      .collect()
+  }
+
+  pub fn holes_count(&self) -> usize {
+    self.paths.walk().filter(|(depth, _)| !depth.is_multiple_of(2)).count()
+  }
+
+  // Point is filled, when the deepest contour around it is at even depth,
+  // the same way `area` and `holes` count them, whichever way contours go around.
+  pub fn contains(&self, point: &Vec2, tolerance: &Tolerance) -> bool {
+    fn depth_around(tree: &Tree<Path>, point: &Vec2, tolerance: &Tolerance) -> Option<usize> {
+      if !tree.data.is_point_inside(point, tolerance) {
+        return None;
+      }
+      let inner = tree.children().iter()
+        .find_map(|child| depth_around(child, point, tolerance))
+        .map(|depth| depth + 1);
+      Some(inner.unwrap_or(0))
+    }
+    depth_around(&self.paths, point, tolerance)
+      .map(|depth| depth.is_multiple_of(2))
+      .unwrap_or(false)
+  }
+
+  // Net area: every nested level cuts out of the one it is nested in,
+  // whichever way its contours go around.
+  pub fn area(&self) -> f32 {
+    fn net(tree: &Tree<Path>) -> f32 {
+      let holes: f32 = tree.children().iter().map(|child| net(child)).sum();
+      tree.data.signed_area().abs() - holes
+    }
+    net(&self.paths)
+  }
+
+  pub fn perimeter(&self) -> f32 {
+    self.contours().into_iter()
+      .map(|path| path.perimeter())
+      .sum()
+  }
+
+  // Mirroring turns contours inside out, so they are reversed to keep their
+  // orientation.
+  pub fn transformed(&self, transform: &Transform) -> Region {
+    let paths = self.paths.map(&|path: &Path| {
+      let path = path.transformed(transform);
+      if transform.is_mirroring() {
+        path.reversed()
+      } else {
+        path
+      }
+    });
+    Region {
+      starting_polirity: self.starting_polirity.clone(),
+      paths,
+      source: self.source.clone()
+    }
+  }
+
+  // Same region with every arc replaced by lines, which are no further than
+  // `max_chord_error` from it. Side of the arcs follows the polarity, so the
+  // region takes no less of the image than it did.
+  pub fn flatten(&self, max_chord_error: f32) -> Region {
+    self.flatten_with_side(max_chord_error, FlattenSide::conservative(&self.starting_polirity))
+  }
+
+  pub fn flatten_with_side(&self, max_chord_error: f32, side: FlattenSide) -> Region {
+    fn flatten_tree(tree: &Tree<Path>, depth: usize, max_chord_error: f32, side: FlattenSide) -> Tree<Path> {
+      let filled_on_left = (tree.data.signed_area() > 0.0) == depth.is_multiple_of(2);
+      let elements = tree.data.elements.iter()
+        .flat_map(|el| match el.algebraic() {
+          AlgebraicPathElement::Line(line) => {
+            let line: Box<dyn StrokePathElement> = Box::new(line);
+            vec!(line)
+          },
+          AlgebraicPathElement::Arc(arc) => {
+            let center_on_left = arc.direction == CircularDirection::CCW;
+            let outer = match side {
+              FlattenSide::Chords => false,
+              FlattenSide::Outside => center_on_left == filled_on_left,
+              FlattenSide::Inside => center_on_left != filled_on_left
+            };
+            let points = if outer {
+              arc.to_outer_polyline(max_chord_error)
+            } else {
+              arc.to_polyline(max_chord_error)
+            };
+            points.windows(2)
+              .map(|pair| {
+                let line: Box<dyn StrokePathElement> = Box::new(Line {
+                  source: arc.source.clone(),
+                  ..Line::new(pair[1], pair[0])
+                });
+                line
+              })
+              .collect()
+          }
+        })
+        .collect();
+      let mut flat = tr(Path::stroke(elements));
+      for child in tree.children().iter() {
+        flat /= flatten_tree(child, depth + 1, max_chord_error, side);
+      }
+      flat
     }
+    Region {
+      starting_polirity: self.starting_polirity.clone(),
+      paths: flatten_tree(&self.paths, 0, max_chord_error, side),
+      source: self.source.clone()
+    }
+  }
+
+  // Region grown by positive distance or shrunk by negative one, it may
+  // break into several or disappear.
+  pub fn offset(&self, distance: f32, tolerance: &Tolerance) -> Vec<Region> {
+    offset(std::slice::from_ref(self), distance, tolerance).into_iter()
+      .map(|region| Region { starting_polirity: self.starting_polirity.clone(), ..region })
+      .collect()
+  }
+
+  // None, if either of the regions has no contours.
+  pub fn distance(&self, other: &Region, tolerance: &Tolerance) -> Option<f32> {
+    distance(std::slice::from_ref(self), std::slice::from_ref(other), tolerance)
+      .map(|(distance, _, _)| distance)
+  }
+
+  pub fn convex_hull(&self, tolerance: &Tolerance) -> Option<Path> {
+    convex_hull(std::slice::from_ref(self), tolerance)
+  }
+
+  pub fn min_area_rectangle(&self, tolerance: &Tolerance) -> Option<OrientedRectangle> {
+    min_area_rectangle(std::slice::from_ref(self), tolerance)
+  }
+
+  pub fn union(&self, other: &Region, tolerance: &Tolerance) -> Vec<Region> {
+    boolean(BooleanOp::Union, std::slice::from_ref(self), std::slice::from_ref(other), tolerance)
+  }
+
+  pub fn intersection(&self, other: &Region, tolerance: &Tolerance) -> Vec<Region> {
+    boolean(BooleanOp::Intersection, std::slice::from_ref(self), std::slice::from_ref(other), tolerance)
+  }
+
+  pub fn difference(&self, other: &Region, tolerance: &Tolerance) -> Vec<Region> {
+    boolean(BooleanOp::Difference, std::slice::from_ref(self), std::slice::from_ref(other), tolerance)
+  }
+
+  pub fn xor(&self, other: &Region, tolerance: &Tolerance) -> Vec<Region> {
+    boolean(BooleanOp::Xor, std::slice::from_ref(self), std::slice::from_ref(other), tolerance)
   }
 }
 
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::Arc;
+  use crate::plotter::fixtures::square_path;
+  use std::f32::consts::PI;
+
+  #[test]
+  fn contains_with_hole() {
+    let mut tree = tr(square_path(0.0, 0.0, 4.0));
+    tree.push(square_path(1.0, 1.0, 2.0).reversed());
+    let region = Region::new(Polarity::Dark, tree);
+    let tolerance = Tolerance::default();
+    assert!(region.contains(&Vec2::new(0.5, 0.5), &tolerance));
+    assert!(!region.contains(&Vec2::new(2.0, 2.0), &tolerance));
+    assert!(!region.contains(&Vec2::new(5.0, 2.0), &tolerance));
+  }
+
+  #[test]
+  fn net_area_and_perimeter() {
+    let mut tree = tr(square_path(0.0, 0.0, 4.0));
+    tree.push(square_path(0.5, 0.5, 1.0).reversed());
+    tree.push(square_path(2.0, 2.0, 1.0));
+    let region = Region::new(Polarity::Dark, tree);
+    assert!((region.area() - 14.0).abs() < 1e-5);
+    assert!((region.perimeter() - 24.0).abs() < 1e-5);
+  }
+
+  #[test]
+  fn transformed_region_keeps_area_and_orientation() {
+    let mut tree = tr(square_path(0.0, 0.0, 4.0));
+    tree.push(square_path(1.0, 1.0, 2.0).reversed());
+    let region = Region::new(Polarity::Dark, tree);
+    let transform = Transform::mirror(false, true)
+      .then(&Transform::rotate(0.7))
+      .then(&Transform::scale(2.0));
+    let moved = region.transformed(&transform);
+    assert!((moved.area() - 48.0).abs() < 1e-3);
+    assert!(moved.paths.data.signed_area() > 0.0);
+    assert!(moved.paths.children()[0].data.signed_area() < 0.0);
+    assert!(moved.contains(&transform.apply(&Vec2::new(0.5, 0.5)), &Tolerance::default()));
+    assert!(!moved.contains(&transform.apply(&Vec2::new(2.0, 2.0)), &Tolerance::default()));
+  }
+
+  #[test]
+  fn flatten_stays_on_requested_side() {
+    let circle = |center: Vec2, radius: f32, direction: CircularDirection| {
+      let start = center + Vec2::new(radius, 0.0);
+      let arc: Box<dyn StrokePathElement> = Box::new(Arc::new_with_fixed_center(start, start, center, direction));
+      Path::stroke(vec!(arc))
+    };
+    let mut tree = tr(circle(Vec2::new(0.0, 0.0), 2.0, CircularDirection::CCW));
+    tree.push(circle(Vec2::new(0.0, 0.0), 1.0, CircularDirection::CW));
+    let region = Region::new(Polarity::Dark, tree);
+    let exact = region.area();
+    let error = 0.01;
+
+    let outside = region.flatten_with_side(error, FlattenSide::Outside);
+    let inside = region.flatten_with_side(error, FlattenSide::Inside);
+    let chords = region.flatten_with_side(error, FlattenSide::Chords);
+    for flat in [&outside, &inside, &chords] {
+      assert!(flat.contours().iter()
+        .flat_map(|path| path.elements.iter())
+        .all(|el| matches!(el.algebraic(), AlgebraicPathElement::Line(_))));
+    }
+    assert!(outside.area() > exact);
+    assert!(inside.area() < exact);
+    assert!((outside.area() - exact).abs() < 2.0 * PI * 3.0 * error);
+    assert!((inside.area() - exact).abs() < 2.0 * PI * 3.0 * error);
+    // Outer contour is more precise, so it has more vertices than the hole
+    assert!(outside.paths.data.elements.len() > outside.paths.children()[0].data.elements.len());
+    assert_eq!(
+      FlattenSide::conservative(&Polarity::Clear),
+      FlattenSide::Inside
+    );
+    assert_eq!(region.flatten(error).area(), outside.area());
+    let clear = Region { starting_polirity: Polarity::Clear, ..region };
+    assert_eq!(clear.flatten(error).area(), inside.area());
+  }
+
+  #[test]
+  fn walk_islands_and_holes() {
+    let mut hole = tr(square_path(1.0, 1.0, 6.0).reversed());
+    hole.push(square_path(2.0, 2.0, 1.0));
+    hole.push(square_path(4.0, 4.0, 1.0));
+    let mut tree = tr(square_path(0.0, 0.0, 8.0));
+    tree /= hole;
+    let region = Region::new(Polarity::Clear, tree);
+
+    let walked: Vec<(usize, Polarity)> = region.walk().into_iter()
+      .map(|contour| (contour.depth, contour.polarity))
+      .collect();
+    assert_eq!(walked, vec!(
+      (0, Polarity::Clear),
+      (1, Polarity::Dark),
+      (2, Polarity::Clear),
+      (2, Polarity::Clear)
+    ));
+    assert_eq!(region.outer_contours().len(), 3);
+    assert_eq!(region.holes_count(), 1);
+    assert_eq!(region.holes()[0].elements[0].get_start_point(), Vec2::new(1.0, 1.0));
+  }
+
+  #[test]
+  fn orientation_is_normalized() {
+    let center = Vec2::new(2.0, 2.0);
+    let start = Vec2::new(3.0, 2.0);
+    let mut tree = tr(square_path(0.0, 0.0, 4.0).reversed());
+    tree.push(Path::stroke(vec!(
+      Box::new(Arc::new_with_fixed_center(start, start, center, CircularDirection::CCW))
+    )));
+    let mut region = Region::new(Polarity::Dark, tree);
+    let area = region.area();
+    region.normalize_orientation();
+    assert!(region.paths.data.is_ccw());
+    let hole = &region.paths.children()[0].data;
+    assert!(!hole.is_ccw());
+    match hole.elements[0].algebraic() {
+      AlgebraicPathElement::Arc(arc) => assert_eq!(arc.direction, CircularDirection::CW),
+      _ => panic!("arc expected")
+    }
+    assert!((region.area() - area).abs() < 1e-5);
+    assert!(region.paths.data.elements.windows(2)
+      .all(|pair| pair[0].get_end_point() == pair[1].get_start_point()));
+  }
+
+  #[test]
+  fn same_oriented_hole_is_hole() {
+    let mut tree = tr(square_path(0.0, 0.0, 4.0).reversed());
+    tree.push(square_path(1.0, 1.0, 2.0).reversed());
+    tree.forest_mut()[0].push(square_path(1.5, 1.5, 1.0).reversed());
+    let region = Region::new(Polarity::Dark, tree);
+    let tolerance = Tolerance::default();
+    assert!(region.contains(&Vec2::new(0.5, 0.5), &tolerance));
+    assert!(!region.contains(&Vec2::new(1.2, 1.2), &tolerance));
+    assert!(region.contains(&Vec2::new(2.0, 2.0), &tolerance));
+    assert_eq!(region.holes_count(), 1);
+    assert!((region.area() - (16.0 - 4.0 + 1.0)).abs() < 1e-5);
+  }
+
+  #[test]
+  fn contains_at_origin_and_in_line_with_vertices() {
+    let region = Region::new(Polarity::Dark, tr(square_path(-1.0, -1.0, 2.0)));
+    assert!(region.contains(&Vec2::new(0.0, 0.0), &Tolerance::default()));
+    let region = Region::new(Polarity::Dark, tr(square_path(0.0, 0.0, 2.0)));
+    assert!(region.contains(&Vec2::new(1.0, 1.0), &Tolerance::default()));
+    assert!(region.contains(&Vec2::new(1.0, 0.5), &Tolerance::default()));
+  }
+
+  #[test]
+  fn contains_within_arcs() {
+    // half disk with the round side to the right
+    let elements: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Arc::new_with_fixed_center(
+        Vec2::new(0.0, 1.0),
+        Vec2::new(0.0, -1.0),
+        Vec2::new(0.0, 0.0),
+        CircularDirection::CCW
+      )),
+      Box::new(Line::new(Vec2::new(0.0, -1.0), Vec2::new(0.0, 1.0))),
+    );
+    let region = Region::new(Polarity::Dark, tr(Path::stroke(elements)));
+    assert!(region.contains(&Vec2::new(0.9, 0.0), &Tolerance::default()));
+    assert!(region.contains(&Vec2::new(0.6, 0.6), &Tolerance::default()));
+    assert!(!region.contains(&Vec2::new(0.8, 0.8), &Tolerance::default()));
+    assert!(!region.contains(&Vec2::new(-0.5, 0.0), &Tolerance::default()));
+  }
+}
@@ -2,17 +2,18 @@ extern crate nalgebra as na;
 use std::collections::HashMap;
 use std::cmp::Ordering;
 use na::*;
-use std::f32::EPSILON;
-use super::region_impl::Region;
+use super::region_impl::{ FillRule, Region };
+use super::validate::{ RegionIssue, RegionIssueKind };
 use crate::parser::Polarity;
 use super::super:: {
   Path,
   AlgebraicPathElement,
-  CircularDirection,
   tree::{ Forest, Tree }
 };
 use super::super::{ 
+  intersection_points,
   StrokePathElement,
+  Tolerance,
    //Path,
   //Algebraic,
   //AlgebraicPathElement,
@@ -20,190 +21,84 @@ use super::super::{
 };
 type Vec2 = Vector2<f32>;
 
-fn is_end_or_start_of_segment(element: &Box<dyn StrokePathElement>, point: &Vec2) ->bool {
-  let point = Point2::new(point.x, point.y);
-  let d1 = na::distance(&element.get_end_point().into(), &point);
-  let d2 = na::distance(&element.get_start_point().into(), &point);
-  // // println!("ddd {}  {}", d1, d2);
-
-
-  d1 <= f32::EPSILON || d2 <= f32::EPSILON
-}
-
-fn is_end_or_start_of_some_segment(element: &Box<dyn StrokePathElement>, element_other: &Box<dyn StrokePathElement>, point: &Vec2) -> bool {
-  is_end_or_start_of_segment(element, point) || is_end_or_start_of_segment(element_other, point)
+fn is_end_or_start_of_segment(element: &dyn StrokePathElement, point: &Vec2, tolerance: &Tolerance) ->bool {
+  tolerance.is_same_point(&element.get_end_point(), point)
+    || tolerance.is_same_point(&element.get_start_point(), point)
 }
 
+fn split_all_primitives_by_intersections(path: Path, tolerance: &Tolerance) -> Path {
+  let points = {
+    let elements: Vec<&dyn StrokePathElement> = path.elements.iter().map(|el| el.as_ref()).collect();
+    intersection_points(&elements, tolerance)
+  };
 
-fn find_intersection_and_elements(path: &Path) -> Option<(Vec<usize>, Vec2)> {
-  let mut result = None;
-
-  'outer: for i in 0..path.elements.len() {
-    if let Some(element) = path.elements.get(i) {
-      for j in i+2..path.elements.len() {
-        if let Some(possible_candidate) = path.elements.get(j) {
-          println!(" inter {:?}", element.get_intersector().intersects(possible_candidate.get_intersector()));
-          if let Some(intersection) = element.get_intersector().intersects(possible_candidate.get_intersector()).pop() {
-            let end_start = (
-              is_end_or_start_of_segment(&element, &intersection),
-              is_end_or_start_of_segment(&possible_candidate, &intersection),
-            );
-            println!("end_start {:?}", end_start);
-            let indexes = match end_start {
-              (false, false) => vec!(i, j),
-              (false, true) => vec!(i),
-              (true, false) => vec!(j),
-              (true, true) => Vec::new()
-            };
-            
-            if !indexes.is_empty() {
-              result = Some((indexes, intersection));
-              break 'outer;
-            }
-          }
-        }
-      }
-    }
-  }
-  result
-}
-
-enum MyResult {
-  New(Path),
-  Old(Path)
-}
-
-fn split_primitives_by_intersections(path: Path) -> MyResult {
-  let intersection = find_intersection_and_elements(&path);
-  println!("found intersection {:?}", intersection);
-
-  if let Some((indexes, split_point)) = intersection {
-    let mut elements: Vec<Box<dyn StrokePathElement>> = Vec::new();
-
-    for (i, element) in path.elements.into_iter().enumerate() {
-      if indexes.contains(&i) {
-        let mut vec = element.split_by(&split_point);
-        elements.append(&mut vec);
+  let elements = path.elements.into_iter()
+    .zip(points)
+    .flat_map(|(element, points)| {
+      let points: Vec<Vec2> = points.into_iter()
+        .filter(|point| !is_end_or_start_of_segment(element.as_ref(), point, tolerance))
+        .collect();
+      if points.is_empty() {
+        vec!(element)
       } else {
-        elements.push(element);
+        element.split_by_points(&points)
       }
-    }
+    })
+    .collect();
 
-    MyResult::New(Path::stroke(elements))
-  } else {
-    MyResult::Old(path)
-  }
+  Path::stroke(elements)
 }
 
-fn split_all_primitives_by_intersections(path: Path) -> Path {
-  use MyResult::*;
-  let mut updated_path = path;
-  loop {
-    match split_primitives_by_intersections(updated_path) {
-      New(new_path) => updated_path = new_path,
-      Old(old_path) => {
-        updated_path = old_path;
-        break;
-      }
-    }
-  }
-  updated_path
-}
-
-fn is_fully_locked(path: &Path) -> bool {
-  let mut map: HashMap<String, usize> = HashMap::new();
-  let from = |el: &dyn StrokePathElement| {
-    let v = el.get_start_point();
-    format!("{:?}", (v.x, v.y))
-  };
-  let to = |el: &dyn StrokePathElement| {
-    let v = el.get_start_point();
-    format!("{:?}", (v.x, v.y))
-  };
-  for el in path.elements.iter() {
-    for tup in vec![from(el.as_ref()), to(el.as_ref())] {
-      match map.get_mut(&tup) {
-        Some(mut_ref) => {*mut_ref+=1;},
-        None => {map.insert(tup, 1);}
-      }
-    }
-  }
-
-  let mut result = true;
-  for (k, v) in map {
-    if v != 2 {
-      result = false;
-      // println!("some spot appeared not twice {}, {}",k, v);
-      break;
-    }
-  }
-
-  result
-}
-
-fn split_by_locked_countours(path: Path) -> Vec<Path>{
+// Leftover, which does not close into a contour, is reported instead of being filled.
+fn split_by_locked_countours(path: Path, tolerance: &Tolerance, issues: &mut Vec<RegionIssue>) -> Vec<Path>{
   let amount_of_elements = path.elements.len();
-  match get_first_found_locked_contour(path) {
-    (Some(_rest), None) => {
-      println!("split unsuccessful, rest path is not locked contour, remove it");
+  match get_first_found_locked_contour(path, tolerance) {
+    (Some(rest), None) => {
+      if let Some(last) = rest.elements.last() {
+        issues.push(RegionIssue::new(RegionIssueKind::Unclosed, last.get_end_point(), last.as_ref()));
+      }
       Vec::new()
     },
     (None, Some(cont)) if cont.elements.len() == amount_of_elements => {
-      println!("full split amount:{}", cont.elements.len());
       vec!(cont)
     },
     (Some(rest), Some(countur)) => {
       // Perhaps, both have more locked things
-      println!("continue split");
-      let mut paths = split_by_locked_countours(rest);
-      paths.extend(split_by_locked_countours(countur));
+      let mut paths = split_by_locked_countours(rest, tolerance, issues);
+      paths.extend(split_by_locked_countours(countur, tolerance, issues));
       paths
     },
     _ => unreachable!("WTF!!!")
   }
 }
 
-fn format_element(element: &Box<dyn StrokePathElement>) -> String {
-  let coords_from =  element.get_start_point();
-  let coords_to =  element.get_end_point();
-  let coords_from = format!("{}; {}", coords_from.x, coords_from.y);
-  let coords_to = format!("{}; {}", coords_to.x, coords_to.y);
-  let t_el = match element.algebraic() {
-    AlgebraicPathElement::Arc(a) => format!("A ({}, {}){} ", a.center.x, a.center.y, match a.direction {
-      CircularDirection::CW => "CW",
-      CircularDirection::CCW => "CCW"
-    }),
-    AlgebraicPathElement::Line(_) => "L".into()
-  };
-  format!("{} ({}, {})", t_el, coords_from, coords_to)
-}
-
-fn find_fist_point_with_two_plus_sources(path: &Path) -> Option<(usize, usize)> {
-  let mut map: HashMap<String, usize> = HashMap::new();
-  let get_stroke_end = |el: &dyn StrokePathElement| {
-    let v = el.get_end_point();
-    format!("{:?}", (v.x, v.y))
-  };
+// Points within tolerance may round into neighbouring cells, so all of them are probed.
+fn find_fist_point_with_two_plus_sources(path: &Path, tolerance: &Tolerance) -> Option<(usize, usize)> {
+  let mut map: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
   let mut found: Option<(usize, usize)>= None;
   for (ix, el) in path.elements.iter().enumerate() {
-    let coord_label_hash = get_stroke_end(el.as_ref());
-    println!("finding spots {} for el ix {}", coord_label_hash, ix);
-    match map.get_mut(&coord_label_hash) {
-      Some(mut_ref) => {
-
-        found = Some((*mut_ref, ix));
+    let end = el.get_end_point();
+    let same_end = tolerance.nearby_keys(&end).iter()
+      .filter_map(|key| map.get(key))
+      .flat_map(|ixs| ixs.iter())
+      .filter(|other| tolerance.is_same_point(&path.elements[**other].get_end_point(), &end))
+      .min();
+    match same_end {
+      Some(other) => {
+
+        found = Some((*other, ix));
         break;
       },
-      None => {map.insert(coord_label_hash, ix);}
+      None => map.entry(tolerance.grid_key(&end)).or_default().push(ix)
     }
   }
   found
 }
 
-fn find_element_with_start<'a>(path: &'a Path, point: &'a Vec2) -> Option<usize> {
+fn find_element_with_start<'a>(path: &'a Path, point: &'a Vec2, tolerance: &Tolerance) -> Option<usize> {
   let mut found: Option<usize> = None;
   for (ix, element) in path.elements.iter().enumerate() {
-    if (element.get_start_point() - point).magnitude() < EPSILON {
+    if tolerance.is_same_point(&element.get_start_point(), point) {
       found = Some(ix);
       break;
     }
@@ -211,7 +106,7 @@ fn find_element_with_start<'a>(path: &'a Path, point: &'a Vec2) -> Option<usize>
   found
 }
 
-fn cutout_from_element(mut consumed_path: Path, element_index: usize) -> (Option<Path>, Option<Path>) {
+fn cutout_from_element(mut consumed_path: Path, element_index: usize, tolerance: &Tolerance) -> (Option<Path>, Option<Path>) {
 
   // println!("cutout from element {}", element_index);
 
@@ -219,12 +114,12 @@ fn cutout_from_element(mut consumed_path: Path, element_index: usize) -> (Option
   let mut next_start_point = consumed_path.elements[element_index].get_end_point();
   // println!("first_start_poing {}", next_start_point);
 
-  while let Some(element_index) = find_element_with_start(&consumed_path, &next_start_point) {
+  while let Some(element_index) = find_element_with_start(&consumed_path, &next_start_point, tolerance) {
     let el = consumed_path.elements.remove(element_index);
     // println!("cutting lille-by-lil {} ({})", element_index, format_element(&el));
     new_path.elements.push(el);
 
-    if new_path.is_locked() {
+    if new_path.is_locked(tolerance) {
       break;
     } else {
       next_start_point = new_path.elements.last().unwrap().get_end_point();
@@ -242,15 +137,11 @@ fn cutout_from_element(mut consumed_path: Path, element_index: usize) -> (Option
 }
 
 
-fn get_first_found_locked_contour(path: Path) -> (Option<Path>, Option<Path>) {
-  match find_fist_point_with_two_plus_sources(&path) {
-    Some((first, _)) => cutout_from_element(path, first),
+fn get_first_found_locked_contour(path: Path, tolerance: &Tolerance) -> (Option<Path>, Option<Path>) {
+  match find_fist_point_with_two_plus_sources(&path, tolerance) {
+    Some((first, _)) => cutout_from_element(path, first, tolerance),
     None => {
-      println!("WTF first {}", format_element(path.elements.first().unwrap()));
-      println!("WTF last {}", format_element(path.elements.last().unwrap()));
-      
-      if path.is_locked() {
-        println!("PATH IS LOCKED");
+      if path.is_locked(tolerance) {
         (None, Some(path))
       } else {
         (Some(path), None)
@@ -313,18 +204,17 @@ fn get_first_found_locked_contour_wrong(path: Path) -> (Path, Option<Path>) {
   }
 }
 
-fn remove_unlocked_and_zero_square_conturs(paths: Vec<Path>) -> Vec<Path> {
+fn remove_unlocked_and_zero_square_conturs(paths: Vec<Path>, tolerance: &Tolerance) -> Vec<Path> {
   paths.into_iter().filter(|path| {
     match path.elements.len() {
       1 => {
         let first = path.elements.first().unwrap();
-        (first.get_start_point() - first.get_end_point()).magnitude() <= EPSILON
+        tolerance.is_same_point(&first.get_start_point(), &first.get_end_point())
       },
       2 => {
         let first = path.elements.first().unwrap();
         let last = path.elements.last().unwrap();
-        let distance = first.get_start_point() - last.get_end_point();
-        if distance.magnitude() <= EPSILON {
+        if tolerance.is_same_point(&first.get_start_point(), &last.get_end_point()) {
           let mut items: Vec<AlgebraicPathElement> = path.elements.iter().map(|el| el.algebraic()).collect();
           let items = (items.pop().unwrap(), items.pop().unwrap());
           use AlgebraicPathElement::*;
@@ -347,7 +237,7 @@ fn remove_unlocked_and_zero_square_conturs(paths: Vec<Path>) -> Vec<Path> {
   }).collect()
 }
 
-fn is_element_has_point_within_path(element: &dyn StrokePathElement, path: &Path) -> bool {
+fn is_element_has_point_within_path(element: &dyn StrokePathElement, path: &Path, tolerance: &Tolerance) -> bool {
   let is_start_on_path = path.elements.iter().filter(|el| {
     // println!("check if point {} {} in path", element.get_start_point().x, element.get_end_point().y);
     el.has_point(&element.get_start_point())
@@ -361,20 +251,20 @@ fn is_element_has_point_within_path(element: &dyn StrokePathElement, path: &Path
     //print!("el start {}, {} ", element.get_start_point().x, element.get_start_point().y);
     //print!("el end {}, {} \n", element.get_end_point().x, element.get_end_point().y);
     // println!("el center {}, {}", element.get_central_point().x, element.get_central_point().y);
-    path.is_point_inside(&element.get_central_point())
+    path.is_point_inside(&element.get_central_point(), tolerance)
   } else if is_start_on_path {
-    path.is_point_inside(&element.get_end_point())
+    path.is_point_inside(&element.get_end_point(), tolerance)
   } else {
 
     let s = element.get_start_point();
-    let ipi = path.is_point_inside(&element.get_start_point());
+    let ipi = path.is_point_inside(&element.get_start_point(), tolerance);
     // println!("is points inside: {} {}  {},",s.x, s.y, ipi);
     ipi
   }
   
 }
 
-pub fn compare_path(path1: &Path, path2: &Path) -> Ordering {
+pub fn compare_path(path1: &Path, path2: &Path, tolerance: &Tolerance) -> Ordering {
   //println!("-----------------------*******************-------------------------");
   for el in path1.elements.iter() {
     //println!("path1 > {}", format_element(&el))
@@ -385,11 +275,11 @@ pub fn compare_path(path1: &Path, path2: &Path) -> Ordering {
   //println!("compare_path1  with {} els with path2 with {} elements", 
            //path1.elements.len(), path2.elements.len());
   let is_path1_within_path2 = path1.elements.iter()
-    .filter(|el| is_element_has_point_within_path(el.as_ref(), path2))
+    .filter(|el| is_element_has_point_within_path(el.as_ref(), path2, tolerance))
     .count() > 0;
   ////println!("======      is path1 within path2  {} ========", is_path1_within_path2);
   let is_path2_within_path1 = path2.elements.iter()
-    .filter(|el| is_element_has_point_within_path(el.as_ref(), path1))
+    .filter(|el| is_element_has_point_within_path(el.as_ref(), path1, tolerance))
     .count() > 0;
   //println!("======      is path2 within path1  {} ========", is_path2_within_path1);
     
@@ -407,14 +297,14 @@ pub fn compare_path(path1: &Path, path2: &Path) -> Ordering {
   }
 }
 
-fn attach_leafs(forest: &mut Forest<Path>, mut paths: Vec<Path>) -> Vec<Path> {
+fn attach_leafs(forest: &mut Forest<Path>, mut paths: Vec<Path>, tolerance: &Tolerance) -> Vec<Path> {
   for node in forest.iter_mut() {
     if node.is_leaf() {
       // println!("node is leaf");
       let (my, rest): (Vec<_>, Vec<_>) = paths.into_iter()
         .partition(|path| {
           // println!(" {:?}", compare_path(&node.data, &path));
-          compare_path(&node.data, &path) == Ordering::Greater
+          compare_path(&node.data, &path, tolerance) == Ordering::Greater
         });
       if !my.is_empty() {
         let local_forest: Forest<Path> = my
@@ -425,24 +315,47 @@ fn attach_leafs(forest: &mut Forest<Path>, mut paths: Vec<Path>) -> Vec<Path> {
       }
       paths = rest;
     } else {
-      paths = attach_leafs(&mut node.forest_mut(), paths);
+      paths = attach_leafs(&mut node.forest_mut(), paths, tolerance);
     }
   };
   paths
 }
 
 
-pub fn compose_regions(mut paths: Vec<Path>) -> Vec<Region> {
-  println!("paths len: {}", paths.len());
+// Contours, which do not change whether area is filled, are not boundaries of
+// the region, so their children are moved to their parents. Winding number
+// inside of a contour grows by one for counterclockwise contour and goes down
+// by one for clockwise one.
+fn keep_fill_boundaries(mut tree: Tree<Path>, outer_winding: i32, fill_rule: FillRule) -> Forest<Path> {
+  let winding = outer_winding + if tree.data.is_ccw() { 1 } else { -1 };
+  let children: Forest<Path> = std::mem::take(tree.forest_mut()).into_iter()
+    .flat_map(|child| keep_fill_boundaries(*child, winding, fill_rule))
+    .collect();
+  if fill_rule.is_filled(winding) == fill_rule.is_filled(outer_winding) {
+    children
+  } else {
+    tree.extend(children);
+    tree.into()
+  }
+}
 
-  if paths.len() == 1 {
+// Contours, which cannot be nested into the others, are reported and left out.
+pub fn compose_regions(
+  mut paths: Vec<Path>,
+  fill_rule: FillRule,
+  tolerance: &Tolerance,
+  issues: &mut Vec<RegionIssue>
+) -> Vec<Region> {
+  if paths.is_empty() {
+    Vec::new()
+  } else if paths.len() == 1 {
     vec!(Region::new(Polarity::Dark, Tree::new(paths.pop().unwrap())))
   } else {
     let (some_top_node_ix, some_top_node) = paths.iter().enumerate()
-      .max_by(|(_,p), (_,y)| compare_path(&p, &y)).unwrap();
+      .max_by(|(_,p), (_,y)| compare_path(&p, &y, tolerance)).unwrap();
     let mut equal_nodes = paths.iter()
       .enumerate()
-      .filter(|(_, n)| compare_path(&some_top_node, n) == Ordering::Equal)
+      .filter(|(_, n)| compare_path(&some_top_node, n, tolerance) == Ordering::Equal)
       .map(|(ix, _)| ix)
       .collect::<Vec<_>>();
     equal_nodes.push(some_top_node_ix);
@@ -455,36 +368,40 @@ pub fn compose_regions(mut paths: Vec<Path>) -> Vec<Region> {
     }
 
     let mut other_nodes: Vec<Path> = other_nodes.into_iter().map(|(_, el)| el).collect();
-    let mut ___handle = other_nodes.len();
-
-
     while !other_nodes.is_empty() {
-      other_nodes = attach_leafs(&mut forest, other_nodes);
-      if other_nodes.len() == ___handle {
-        panic!("adfasdf");
+      let left = other_nodes.len();
+      other_nodes = attach_leafs(&mut forest, other_nodes, tolerance);
+      if other_nodes.len() == left {
+        for path in other_nodes.iter() {
+          let first = path.elements[0].as_ref();
+          issues.push(RegionIssue::new(RegionIssueKind::Unnested, first.get_start_point(), first));
+        }
+        break;
       }
     }
 
-    forest.into_iter().map(|tr| {
-      Region::new(Polarity::Dark, *tr)
-    }).collect::<Vec<Region>>()
+    forest.into_iter()
+      .flat_map(|tr| keep_fill_boundaries(*tr, 0, fill_rule))
+      .map(|tr| Region::new(Polarity::Dark, *tr))
+      .collect::<Vec<Region>>()
   }
 
 }
 
-pub fn split_region_paths(path: Path) -> Vec<Region> {
-  let splitted_path = split_all_primitives_by_intersections(path);
-  let contours = split_by_locked_countours(splitted_path);
-  for c in &contours {
-    println!("\ncontour {:?}\n", c);
-  }
-  let contours = remove_unlocked_and_zero_square_conturs(contours);
-
-  let regs = compose_regions(contours);
-  //println!("Regs are good");
+pub fn split_region_paths(path: Path, tolerance: &Tolerance, fill_rule: FillRule) -> Vec<Region> {
+  split_region_paths_with_issues(path, tolerance, fill_rule).0
+}
 
-  regs
+// Regions along with parts of the path, which could not be closed into them.
+pub fn split_region_paths_with_issues(mut path: Path, tolerance: &Tolerance, fill_rule: FillRule) -> (Vec<Region>, Vec<RegionIssue>) {
+  path.weld(tolerance);
+  let splitted_path = split_all_primitives_by_intersections(path, tolerance);
+  let mut issues = Vec::new();
+  let contours = split_by_locked_countours(splitted_path, tolerance, &mut issues);
+  let contours = remove_unlocked_and_zero_square_conturs(contours, tolerance);
 
+  let regions = compose_regions(contours, fill_rule, tolerance, &mut issues);
+  (regions, issues)
 }
 
 #[cfg(test)]
@@ -492,26 +409,24 @@ mod tests {
   use super::*;
   use crate::plotter::{ Arc, Line };
   use crate::plotter::{ StrokePathElement, CircularDirection };
-  use std::f32::EPSILON;
+  use crate::plotter::fixtures::square_path;
 
   #[test]
-  fn find_intersection_and_elements_test() {
+  fn intersections_of_path_elements() {
     let elements: Vec<Box<dyn StrokePathElement>> = vec!(
       Box::new(Line::new(Vec2::new(5.0, 0.0), Vec2::new(-5.0, 10.0))),
       Box::new(Line::new(Vec2::new(0.0, 0.0), Vec2::new(5.0, 0.0))),
       Box::new(Line::new(Vec2::new(5.0, 5.0), Vec2::new(0.0, 0.0))),
       );
-    let path = Path::stroke(elements);
-
-
-    if let Some((u, ve)) = find_intersection_and_elements(&path) {
-      println!("some staff found {:?}, {:?}", u, ve);
-      assert_eq!(u[0], 0);
-      assert_eq!(u[1], 2);
-      assert_eq!((ve - Vec2::new(2.5, 2.5)).magnitude() < EPSILON, true);
-    } else {
-      panic!("Cannot find intersection");
-    }
+    let refs: Vec<&dyn StrokePathElement> = elements.iter().map(|el| el.as_ref()).collect();
+    let tolerance = Tolerance::default();
+    let points = intersection_points(&refs, &tolerance);
+    let crossing = points[0].iter()
+      .find(|p| !is_end_or_start_of_segment(elements[0].as_ref(), p, &tolerance))
+      .unwrap();
+    assert!(tolerance.is_same_point(crossing, &Vec2::new(2.5, 2.5)));
+    assert!(points[2].iter().any(|p| tolerance.is_same_point(p, crossing)));
+    assert!(points[1].iter().all(|p| is_end_or_start_of_segment(elements[1].as_ref(), p, &tolerance)));
   }
 
   #[test]
@@ -522,10 +437,21 @@ mod tests {
       Box::new(Line::new(Vec2::new(5.0, 5.0), Vec2::new(0.0, 0.0))),
       );
     let path = Path::stroke(elements);
-    let new_path = super::split_all_primitives_by_intersections(path);
+    let new_path = super::split_all_primitives_by_intersections(path, &Tolerance::default());
     assert_eq!(new_path.elements.len(), 5);
   }
 
+  #[test]
+  fn split_collinear_overlap() {
+    let elements: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Line::new(Vec2::new(4.0, 0.0), Vec2::new(0.0, 0.0))),
+      Box::new(Line::new(Vec2::new(1.0, 0.0), Vec2::new(3.0, 0.0))),
+      );
+    let path = Path::stroke(elements);
+    let new_path = super::split_all_primitives_by_intersections(path, &Tolerance::default());
+    assert_eq!(new_path.elements.len(), 4);
+  }
+
   #[test]
   fn get_first_found_locked_contour() {
     let elements: Vec<Box<dyn StrokePathElement>> = vec!(
@@ -534,8 +460,8 @@ mod tests {
       Box::new(Line::new(Vec2::new(5.0, 5.0), Vec2::new(0.0, 0.0))),
       );
     let path = Path::stroke(elements);
-    let path = super::split_all_primitives_by_intersections(path);
-    let (rest, new_path) = super::get_first_found_locked_contour(path);
+    let path = super::split_all_primitives_by_intersections(path, &Tolerance::default());
+    let (rest, new_path) = super::get_first_found_locked_contour(path, &Tolerance::default());
     if let Some(path) = new_path {
       assert_eq!(path.elements.len(), 3);
       assert_eq!(rest.unwrap().elements.len(), 2);
@@ -544,6 +470,23 @@ mod tests {
     }
   }
 
+  #[test]
+  fn same_point_across_grid_cells() {
+    let tolerance = Tolerance::new(0.001);
+    // both ends are within tolerance, but round to different cells
+    let one = Vec2::new(0.000_499_9, 0.0);
+    let two = Vec2::new(0.000_500_1, 0.0);
+    assert_ne!(tolerance.grid_key(&one), tolerance.grid_key(&two));
+    let elements: Vec<Box<dyn StrokePathElement>> = vec!(
+      Box::new(Line::new(one, Vec2::new(-1.0, -1.0))),
+      Box::new(Line::new(Vec2::new(1.0, 1.0), one)),
+      Box::new(Line::new(two, Vec2::new(1.0, 1.0))),
+      Box::new(Line::new(Vec2::new(-1.0, -1.0), two)),
+      );
+    let path = Path::stroke(elements);
+    assert_eq!(find_fist_point_with_two_plus_sources(&path, &tolerance), Some((0, 2)));
+  }
+
   #[test]
   fn get_first_found_locked_contour_full_arc() {
     let elements: Vec<Box<dyn StrokePathElement>> = vec!(
@@ -556,8 +499,8 @@ mod tests {
           ))
     );
     let path = Path::stroke(elements);
-    let path = super::split_all_primitives_by_intersections(path);
-    let (rest, new_path) = super::get_first_found_locked_contour(path);
+    let path = super::split_all_primitives_by_intersections(path, &Tolerance::default());
+    let (rest, new_path) = super::get_first_found_locked_contour(path, &Tolerance::default());
     if let Some(path) = new_path {
       assert_eq!(path.elements.len(), 1);
       assert_eq!(rest.unwrap().elements.len(), 1);
@@ -578,8 +521,12 @@ mod tests {
           ))
     );
     let path = Path::stroke(elements);
-    let paths = super::split_by_locked_countours(path);
+    let mut issues = Vec::new();
+    let paths = super::split_by_locked_countours(path, &Tolerance::default(), &mut issues);
     assert_eq!(paths.len(), 1);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RegionIssueKind::Unclosed);
+    assert_eq!(issues[0].location, Vec2::new(0.0, -5.0));
   }
 
   #[test]
@@ -590,9 +537,13 @@ mod tests {
       Box::new(Line::new(Vec2::new(5.0, 5.0), Vec2::new(0.0, 0.0))),
     );
     let path = Path::stroke(elements);
-    let path = super::split_all_primitives_by_intersections(path);
-    let paths = super::split_by_locked_countours(path);
+    let path = super::split_all_primitives_by_intersections(path, &Tolerance::default());
+    let mut issues = Vec::new();
+    let paths = super::split_by_locked_countours(path, &Tolerance::default(), &mut issues);
     assert_eq!(paths.len(), 1);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RegionIssueKind::Unclosed);
+    assert_eq!(issues[0].location, Vec2::new(5.0, 5.0));
   }
 
   #[test]
@@ -604,7 +555,7 @@ mod tests {
     );
 
     let path = Path::stroke(elements);
-    let mut regions = super::split_region_paths(path);
+    let mut regions = super::split_region_paths(path, &Tolerance::default(), FillRule::NonZero);
 
     assert!(regions.len() == 1);
     let Region{ paths, ..} = regions.remove(0);
@@ -623,7 +574,7 @@ mod tests {
     );
 
     let path = Path::stroke(elements);
-    let mut regions = super::split_region_paths(path);
+    let mut regions = super::split_region_paths(path, &Tolerance::default(), FillRule::NonZero);
 
     assert_eq!(regions.len(), 1);
     let Region{ paths, ..} = regions.remove(0);
@@ -652,7 +603,7 @@ mod tests {
       Box::new(Line::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 5.0))),
     );
     let path = Path::stroke(elements);
-    let mut regions = super::split_region_paths(path);
+    let mut regions = super::split_region_paths(path, &Tolerance::default(), FillRule::NonZero);
 
     assert_eq!(regions.len(), 1);
     let Region{ paths, ..} = regions.remove(0);
@@ -661,6 +612,49 @@ mod tests {
     assert!(iter.next().unwrap().data.elements.len() == 1);
   }
 
+  #[test]
+  fn overlapping_loops_follow_fill_rule() {
+    // the triangle at the bottom is gone around twice
+    let points = [
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(0.2, 0.2),
+      Vec2::new(0.8, 0.2),
+      Vec2::new(0.0, 1.0),
+    ];
+    let contour = || Path::stroke((0..6)
+      .map(|ix| {
+        let line: Box<dyn StrokePathElement> = Box::new(Line::new(points[(ix + 1) % 6], points[ix]));
+        line
+      })
+      .collect());
+
+    let regions = super::split_region_paths(contour(), &Tolerance::default(), FillRule::NonZero);
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].contours().len(), 1);
+    assert!((regions[0].area() - 0.75).abs() < 1e-4);
+
+    let regions = super::split_region_paths(contour(), &Tolerance::default(), FillRule::EvenOdd);
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].holes_count(), 1);
+    assert!((regions[0].area() - 0.66).abs() < 1e-4);
+  }
+
+  #[test]
+  fn crossing_contours_are_reported() {
+    let mut issues = Vec::new();
+    let regions = super::compose_regions(
+      vec!(square_path(0.0, 0.0, 2.0), square_path(1.0, 1.0, 2.0)),
+      FillRule::NonZero,
+      &Tolerance::default(),
+      &mut issues
+    );
+    assert_eq!(regions.len(), 1);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RegionIssueKind::Unnested);
+  }
+
   #[test]
   fn for_loop() {
     let mut a = 0;
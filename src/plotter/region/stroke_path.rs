@@ -1,34 +1,148 @@
 use std::cmp::Ordering;
 
 use super::{
+  FillRule,
   Region,
+  RegionIssueKind,
   compare_path,
+  split_region_paths,
+  validate_contour,
   super::{ 
     Path,
+    PathType,
+    Vec2,
     StrokePathElement,
+    AlgebraicPathElement,
+    CircularDirection,
+    Tolerance,
     tr
   }
 };
 
-use crate::parser::Polarity;
+use crate::parser::{ Polarity, SourceSpan };
 
-pub fn to_stroke_around_path(path: Path) -> Vec<Region> {
+// Aperture touches the stroke outline with the same vertex only while normal of the
+// element does not cross any of aperture's edge normals, so arcs are split there.
+fn split_by_aperture(element: Box<dyn StrokePathElement>, path_type: &PathType) -> Vec<Box<dyn StrokePathElement>> {
+  match element.algebraic() {
+    AlgebraicPathElement::Arc(arc) => {
+      let radius = arc.get_radius();
+      let points: Vec<Vec2> = path_type.aperture_shape().critical_angles().into_iter()
+        .flat_map(|angle| {
+          let (y, x) = angle.sin_cos();
+          let v = radius * Vec2::new(x, y);
+          vec!(arc.center + v, arc.center - v)
+        })
+        .filter(|point| {
+          let t = element.parameter_of(point);
+          t > 1e-5 && t < 1.0 - 1e-5
+        })
+        .collect();
+      if points.is_empty() {
+        vec!(element)
+      } else {
+        element.split_by_points(&points)
+      }
+    },
+    AlgebraicPathElement::Line(_) => vec!(element)
+  }
+}
+
+fn is_zero_length(element: &dyn StrokePathElement, tolerance: &Tolerance) -> bool {
+  match element.algebraic() {
+    AlgebraicPathElement::Line(line) => tolerance.is_same_point(&line.to, &line.from),
+    AlgebraicPathElement::Arc(arc) => tolerance.is_zero(arc.get_radius())
+  }
+}
+
+// Full circle, which is drawn with aperture not smaller than circle itself leaves no hole.
+fn is_solid_circle(elements: &[Box<dyn StrokePathElement>], path_type: &PathType, tolerance: &Tolerance) -> bool {
+  match elements {
+    [element] => match element.algebraic() {
+      AlgebraicPathElement::Arc(arc) => {
+        tolerance.is_same_point(&arc.to, &arc.from) &&
+          arc.get_radius() <= path_type.aperture_shape().circumradius()
+      },
+      AlgebraicPathElement::Line(_) => false
+    },
+    _ => false
+  }
+}
+
+// Caps and joins do not come from any command, so they are attributed to the element they belong to.
+fn with_source(
+  elements: Vec<Box<dyn StrokePathElement>>,
+  source: Option<&SourceSpan>
+) -> Vec<Box<dyn StrokePathElement>> {
+  elements.into_iter().map(|mut element| {
+    if element.get_source().is_none() {
+      element.set_source(source.cloned());
+    }
+    element
+  }).collect()
+}
+
+// Aperture swept along an arc tighter than itself makes an outline, which overlaps itself.
+fn is_self_overlapping(path: &Path, tolerance: &Tolerance) -> bool {
+  validate_contour(path, tolerance).iter()
+    .any(|issue| issue.kind == RegionIssueKind::SelfIntersection || issue.kind == RegionIssueKind::InvalidCutIn)
+}
+
+pub fn to_stroke_around_path(path: Path, tolerance: &Tolerance) -> Vec<Region> {
   let mut forward: Vec<Box<dyn StrokePathElement>> = Vec::new();
   let mut backward: Vec<Box<dyn StrokePathElement>> = Vec::new();
-  let is_locked = path.is_locked();
+  let Path{tp, elements} = path;
+  let (at, source) = match elements.first() {
+    Some(element) => (element.get_start_point(), element.get_source().cloned()),
+    None => return Vec::new()
+  };
+  let elements: Vec<Box<dyn StrokePathElement>> = elements.into_iter()
+    .filter(|element| !is_zero_length(element.as_ref(), tolerance))
+    .collect();
+  if elements.is_empty() {
+    let footprint = Path::stroke(with_source(tp.aperture_shape().outline(&at), source.as_ref()));
+    return vec!(Region::new(Polarity::Dark, tr(footprint)));
+  }
+  let is_solid = is_solid_circle(&elements, &tp, tolerance);
+  let is_ccw = match elements[0].algebraic() {
+    AlgebraicPathElement::Arc(arc) => arc.direction == CircularDirection::CCW,
+    AlgebraicPathElement::Line(_) => false
+  };
+  let path = Path {
+    elements: elements.into_iter()
+      .flat_map(|element| split_by_aperture(element, &tp))
+      .collect(),
+    tp
+  };
+  let is_locked = path.is_locked(tolerance);
   let Path{tp, elements} = path;
 
-  for ix in 0..elements.len() {
-    let prev = if ix == 0 { None } else {elements.get(ix - 1).map(|i| i.as_ref())};
+  // closed track joins its last element with the first one as any other two
+  let count = elements.len();
+  let is_wrapped = is_locked && count > 1;
+  for ix in 0..count {
+    let prev = match ix {
+      0 if is_wrapped => Some(elements[count - 1].as_ref()),
+      0 => None,
+      ix => Some(elements[ix - 1].as_ref())
+    };
     let current = &elements[ix];
-    let next = elements.get(ix + 1).map(|i| i.as_ref());
-    forward.extend(current.forward(&tp, prev, next, is_locked));
-    backward.extend(current.backward(&tp, prev, next));
+    let next = match ix + 1 {
+      ix if ix < count => Some(elements[ix].as_ref()),
+      _ if is_wrapped => Some(elements[0].as_ref()),
+      _ => None
+    };
+    forward.extend(with_source(current.forward(&tp, prev, next, is_locked), current.get_source()));
+    backward.extend(with_source(current.backward(&tp, prev, next), current.get_source()));
   }
-  if is_locked {
+  backward.reverse();
+  if is_solid {
+    let outer = if is_ccw { backward } else { forward };
+    vec!(Region::new(Polarity::Dark, tr(Path::stroke(outer))))
+  } else if is_locked {
     let forward = Path::stroke(forward);
     let backward = Path::stroke(backward);
-    match compare_path(&forward, &backward) {
+    match compare_path(&forward, &backward, tolerance) {
       Ordering::Greater => vec!(Region::new(Polarity::Dark, tr(forward) / tr(backward))),
       Ordering::Less => vec!(Region::new(Polarity::Dark, tr(backward) / tr(forward))),
       Ordering::Equal => vec!(
@@ -37,10 +151,237 @@ pub fn to_stroke_around_path(path: Path) -> Vec<Region> {
         ),
     }
   } else {
-    backward.reverse();
     forward.extend(backward);
 
-    let path = Path::stroke(elements);
-    vec!(Region::new(Polarity::Dark, tr(path)))
+    let path = Path::stroke(forward);
+    if is_self_overlapping(&path, tolerance) {
+      split_region_paths(path, tolerance, FillRule::NonZero)
+    } else {
+      vec!(Region::new(Polarity::Dark, tr(path)))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::super::{ Line, Arc, CircularDirection };
+
+  fn outline(tp: PathType, elements: Vec<Box<dyn StrokePathElement>>) -> Vec<Box<dyn StrokePathElement>> {
+    let mut path = Path::new(tp);
+    for element in elements {
+      path.add(element);
+    }
+    let mut regions = to_stroke_around_path(path, &Tolerance::default());
+    assert_eq!(regions.len(), 1);
+    regions.remove(0).paths.data.elements
+  }
+
+  fn assert_closed(elements: &[Box<dyn StrokePathElement>]) {
+    for ix in 0..elements.len() {
+      let next = &elements[(ix + 1) % elements.len()];
+      let gap = elements[ix].get_end_point() - next.get_start_point();
+      assert!(gap.magnitude() < 1e-5, "gap after element {}: {:?}", ix, gap);
+    }
+  }
+
+  fn has_point(elements: &[Box<dyn StrokePathElement>], point: Vec2) -> bool {
+    elements.iter().any(|e| (e.get_end_point() - point).magnitude() < 1e-5)
+  }
+
+  #[test]
+  fn obround_stroke_across_long_side() {
+    let elements = outline(
+      PathType::Obround(0.4, 0.2),
+      vec!(Box::new(Line::new(Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0))))
+    );
+    assert_closed(&elements);
+    assert!(has_point(&elements, Vec2::new(0.2, 0.0)));
+    assert!(has_point(&elements, Vec2::new(0.2, 1.0)));
+    assert!(has_point(&elements, Vec2::new(-0.2, 1.0)));
+    assert!(has_point(&elements, Vec2::new(-0.2, 0.0)));
+  }
+
+  #[test]
+  fn polygon_stroke_keeps_vertices() {
+    let elements = outline(
+      PathType::Polygon(2.0, 4, 0.0),
+      vec!(Box::new(Line::new(Vec2::new(3.0, 0.0), Vec2::new(0.0, 0.0))))
+    );
+    assert_closed(&elements);
+    assert!(has_point(&elements, Vec2::new(-1.0, 0.0)));
+    assert!(has_point(&elements, Vec2::new(0.0, 1.0)));
+    assert!(has_point(&elements, Vec2::new(3.0, 1.0)));
+    assert!(has_point(&elements, Vec2::new(4.0, 0.0)));
+    assert!(has_point(&elements, Vec2::new(3.0, -1.0)));
+    assert!(has_point(&elements, Vec2::new(0.0, -1.0)));
+  }
+
+  #[test]
+  fn rect_stroke_along_diagonal_is_hexagon() {
+    let elements = outline(
+      PathType::Rect(0.2, 0.1),
+      vec!(Box::new(Line::new(Vec2::new(0.5, 0.3), Vec2::new(0.0, 0.0))))
+    );
+    assert_closed(&elements);
+    assert_eq!(elements.len(), 6);
+    for point in [
+      Vec2::new(-0.1, -0.05), Vec2::new(-0.1, 0.05), Vec2::new(0.4, 0.35),
+      Vec2::new(0.6, 0.35), Vec2::new(0.6, 0.25), Vec2::new(0.1, -0.05)
+    ] {
+      assert!(has_point(&elements, point));
+    }
+  }
+
+  #[test]
+  fn rect_stroke_around_corner() {
+    let elements = outline(
+      PathType::Rect(0.2, 0.1),
+      vec!(
+        Box::new(Line::new(Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0))),
+        Box::new(Line::new(Vec2::new(1.0, 1.0), Vec2::new(1.0, 0.0)))
+      )
+    );
+    assert_closed(&elements);
+    assert_eq!(elements.len(), 6);
+    assert!(has_point(&elements, Vec2::new(0.9, 0.05)));
+    assert!(has_point(&elements, Vec2::new(1.1, -0.05)));
+  }
+
+  #[test]
+  fn rect_stroke_along_arc() {
+    let arc = Arc::new_with_fixed_center(
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      CircularDirection::CCW
+    );
+    let elements = outline(PathType::Rect(0.2, 0.1), vec!(Box::new(arc)));
+    assert_closed(&elements);
+    let centers: Vec<Vec2> = elements.iter().filter_map(|e| match e.algebraic() {
+      AlgebraicPathElement::Arc(arc) => Some(arc.center),
+      AlgebraicPathElement::Line(_) => None
+    }).collect();
+    assert_eq!(centers.len(), 2);
+    assert!(centers.contains(&Vec2::new(-0.1, -0.05)));
+    assert!(centers.contains(&Vec2::new(0.1, 0.05)));
+  }
+
+  #[test]
+  fn rect_stroke_along_arc_narrower_than_aperture() {
+    // inner offsets of both halves of the arc cross in their common end, so nothing of them is left
+    let arc = Arc::new_with_fixed_center(
+      Vec2::new(0.25, 0.0),
+      Vec2::new(-0.25, 0.0),
+      Vec2::new(0.0, -0.4),
+      CircularDirection::CW
+    );
+    let mut path = Path::new(PathType::Rect(0.5, 0.25));
+    path.add(Box::new(arc));
+    let regions = to_stroke_around_path(path, &Tolerance::default());
+    assert_eq!(regions.len(), 1);
+    let elements = &regions[0].paths.data.elements;
+    assert_closed(elements);
+    assert!(elements.iter().all(|e| e.length() < 1.0));
+    assert!((regions[0].area() - 0.310_138).abs() < 1e-5);
+  }
+
+  #[test]
+  fn obround_stroke_along_arc_tighter_than_aperture() {
+    let mut path = Path::new(PathType::Obround(0.01, 2.0));
+    path.add(Box::new(Line::new(Vec2::new(-0.4, 0.0), Vec2::new(-1.0, 0.0))));
+    path.add(Box::new(Arc::new_with_fixed_center(
+      Vec2::new(0.0, 0.4),
+      Vec2::new(-0.4, 0.0),
+      Vec2::new(0.0, 0.0),
+      CircularDirection::CCW
+    )));
+    path.add(Box::new(Line::new(Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.4))));
+    let regions = to_stroke_around_path(path, &Tolerance::default());
+    assert_eq!(regions.len(), 1);
+    for contour in regions[0].contours() {
+      assert!(validate_contour(contour, &Tolerance::default()).is_empty());
+    }
+    assert!((regions[0].area() - 3.085_856).abs() < 1e-4);
+  }
+
+  #[test]
+  fn zero_length_draw_is_footprint() {
+    let at = Vec2::new(1.0, 1.0);
+    let elements = outline(PathType::Rect(0.5, 0.25), vec!(Box::new(Line::new(at, at))));
+    assert_closed(&elements);
+    assert_eq!(elements.len(), 4);
+    assert!(has_point(&elements, Vec2::new(0.75, 0.875)));
+    assert!(has_point(&elements, Vec2::new(1.25, 1.125)));
+
+    let elements = outline(PathType::Circle(0.25), vec!(Box::new(Line::new(at, at))));
+    assert_eq!(elements.len(), 1);
+    match elements[0].algebraic() {
+      AlgebraicPathElement::Arc(arc) => {
+        assert_eq!(arc.center, at);
+        assert_eq!(arc.get_radius(), 0.25);
+      },
+      AlgebraicPathElement::Line(_) => panic!("Footprint of circle must be an arc")
+    }
+  }
+
+  #[test]
+  fn closed_track_is_ring_without_gap() {
+    let corners = [Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0)];
+    let rounded = 4.0 * 0.0025 - std::f32::consts::PI * 0.0025;
+    for (tp, expected) in [
+      (PathType::Circle(0.05), 1.21 - rounded - 0.81),
+      (PathType::Rect(0.1, 0.1), 1.21 - 0.81),
+      (PathType::Obround(0.1, 0.2), 1.1 * 1.2 - rounded - 0.9 * 0.8),
+      (PathType::Polygon(0.2, 4, 0.0), 1.44 - 4.0 * 0.005 - 0.64)
+    ] {
+      let mut path = Path::new(tp.clone());
+      for ix in 0..4 {
+        path.add(Box::new(Line::new(corners[(ix + 1) % 4], corners[ix])));
+      }
+      let regions = to_stroke_around_path(path, &Tolerance::default());
+      assert_eq!(regions.len(), 1);
+      assert_eq!(regions[0].holes_count(), 1, "{:?}", tp);
+      for contour in regions[0].contours() {
+        assert_closed(&contour.elements);
+      }
+      assert!((regions[0].area() - expected).abs() < 1e-4, "{:?}: {}", tp, regions[0].area());
+    }
+  }
+
+  fn full_circle(radius: f32) -> Box<dyn StrokePathElement> {
+    let from = Vec2::new(radius, 0.0);
+    Box::new(Arc::new_with_fixed_center(from, from, Vec2::new(0.0, 0.0), CircularDirection::CCW))
+  }
+
+  #[test]
+  fn full_circle_is_annulus() {
+    let mut path = Path::new(PathType::Circle(0.1));
+    path.add(full_circle(1.0));
+    let mut regions = to_stroke_around_path(path, &Tolerance::default());
+    assert_eq!(regions.len(), 1);
+    let tree = regions.remove(0).paths;
+    let radius_of = |path: &Path| match path.elements[0].algebraic() {
+      AlgebraicPathElement::Arc(arc) => arc.get_radius(),
+      AlgebraicPathElement::Line(_) => panic!("Circle expected")
+    };
+    assert!((radius_of(&tree.data) - 1.1).abs() < 1e-6);
+    let holes: Vec<_> = tree.into_iter().collect();
+    assert_eq!(holes.len(), 1);
+    assert!((radius_of(&holes[0].data) - 0.9).abs() < 1e-6);
+  }
+
+  #[test]
+  fn full_circle_within_aperture_is_disk() {
+    let mut path = Path::new(PathType::Circle(0.5));
+    path.add(full_circle(0.25));
+    let mut regions = to_stroke_around_path(path, &Tolerance::default());
+    assert_eq!(regions.len(), 1);
+    let tree = regions.remove(0).paths;
+    assert!(tree.is_leaf());
+    match tree.data.elements[0].algebraic() {
+      AlgebraicPathElement::Arc(arc) => assert!((arc.get_radius() - 0.75).abs() < 1e-6),
+      AlgebraicPathElement::Line(_) => panic!("Circle expected")
+    }
   }
 }
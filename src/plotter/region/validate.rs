@@ -0,0 +1,186 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use crate::parser::SourceSpan;
+use super::super::{
+  bounds,
+  Bvh,
+  Contact,
+  Path,
+  StrokePathElement,
+  Tolerance,
+};
+
+type Vec2 = Vector2<f32>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegionIssueKind {
+  // Contour does not end where it starts.
+  Unclosed,
+  // Contour crosses or touches itself not in a vertex of both elements.
+  SelfIntersection,
+  // Contour is thinner than tolerance everywhere, so it has no area.
+  Sliver,
+  // Elements overlap, but are not the same element gone in opposite directions.
+  InvalidCutIn,
+  // Contour split out of the region is neither inside nor outside of the others.
+  Unnested
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionIssue {
+  pub kind: RegionIssueKind,
+  pub location: Vec2,
+  pub source: Option<SourceSpan>
+}
+
+impl RegionIssue {
+  pub(crate) fn new(kind: RegionIssueKind, location: Vec2, element: &dyn StrokePathElement) -> Self {
+    RegionIssue { kind, location, source: element.get_source().cloned() }
+  }
+}
+
+fn is_vertex(element: &dyn StrokePathElement, point: &Vec2, tolerance: &Tolerance) -> bool {
+  tolerance.is_same_point(&element.get_start_point(), point)
+    || tolerance.is_same_point(&element.get_end_point(), point)
+}
+
+fn is_reversed(one: &dyn StrokePathElement, two: &dyn StrokePathElement, tolerance: &Tolerance) -> bool {
+  tolerance.is_same_point(&one.get_start_point(), &two.get_end_point())
+    && tolerance.is_same_point(&one.get_end_point(), &two.get_start_point())
+}
+
+fn point_issue(
+  one: &dyn StrokePathElement,
+  two: &dyn StrokePathElement,
+  point: Vec2,
+  tolerance: &Tolerance
+) -> Option<RegionIssue> {
+  if is_vertex(one, &point, tolerance) && is_vertex(two, &point, tolerance) {
+    None
+  } else {
+    Some(RegionIssue::new(RegionIssueKind::SelfIntersection, point, two))
+  }
+}
+
+fn pair_issues(
+  one: &dyn StrokePathElement,
+  two: &dyn StrokePathElement,
+  tolerance: &Tolerance
+) -> Vec<RegionIssue> {
+  one.get_intersector().contacts(two.get_intersector(), tolerance.distance).into_iter()
+    .filter_map(|contact| match contact {
+      Contact::Overlap(from, to) if tolerance.is_same_point(&from, &to) => point_issue(one, two, from, tolerance),
+      // cut-in goes the whole element back
+      Contact::Overlap(from, to) if is_reversed(one, two, tolerance)
+        && is_vertex(two, &from, tolerance)
+        && is_vertex(two, &to, tolerance) => None,
+      Contact::Overlap(from, _) => Some(RegionIssue::new(RegionIssueKind::InvalidCutIn, from, two)),
+      Contact::Point(point) | Contact::Tangent(point) => point_issue(one, two, point, tolerance)
+    })
+    .collect()
+}
+
+// Problems of a contour of G36/G37 region, which are to be found before it
+// is split into regions: these drop or misplace parts of the contour.
+pub fn validate_contour(path: &Path, tolerance: &Tolerance) -> Vec<RegionIssue> {
+  let elements: Vec<&dyn StrokePathElement> = path.elements.iter()
+    .map(|el| el.as_ref())
+    .filter(|el| !tolerance.is_zero(el.length()))
+    .collect();
+  let (first, last) = match (elements.first(), elements.last()) {
+    (Some(first), Some(last)) => (*first, *last),
+    _ => return Vec::new()
+  };
+
+  let mut issues = Vec::new();
+  if !tolerance.is_same_point(&first.get_start_point(), &last.get_end_point()) {
+    issues.push(RegionIssue::new(RegionIssueKind::Unclosed, last.get_end_point(), last));
+  }
+
+  let boxes: Vec<_> = elements.iter().map(|el| bounds(*el, tolerance)).collect();
+  let index = Bvh::new(&boxes);
+  for (ix, element) in elements.iter().enumerate() {
+    let mut others = index.in_window(&boxes[ix]);
+    others.retain(|other| *other > ix);
+    others.sort();
+    for other in others {
+      issues.extend(pair_issues(*element, elements[other], tolerance));
+    }
+  }
+
+  if issues.is_empty() {
+    let perimeter: f32 = elements.iter().map(|el| el.length()).sum();
+    if path.signed_area().abs() <= 0.5 * perimeter * tolerance.distance {
+      issues.push(RegionIssue::new(RegionIssueKind::Sliver, first.get_start_point(), first));
+    }
+  }
+  issues
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::{ Arc, CircularDirection, Line };
+
+  fn polyline(points: &[Vec2]) -> Path {
+    Path::stroke(points.windows(2)
+      .map(|pair| {
+        let line: Box<dyn StrokePathElement> = Box::new(Line::new(pair[1], pair[0]));
+        line
+      })
+      .collect())
+  }
+
+  fn kinds(path: &Path) -> Vec<RegionIssueKind> {
+    validate_contour(path, &Tolerance::default()).into_iter().map(|issue| issue.kind).collect()
+  }
+
+  #[test]
+  fn valid_contours() {
+    let square = polyline(&[
+      Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), Vec2::new(4.0, 4.0), Vec2::new(0.0, 4.0), Vec2::new(0.0, 0.0)
+    ]);
+    assert!(kinds(&square).is_empty());
+
+    // square with a square hole, connected by a cut-in along y = 2
+    let with_cut_in = polyline(&[
+      Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), Vec2::new(4.0, 2.0), Vec2::new(3.0, 2.0),
+      Vec2::new(3.0, 3.0), Vec2::new(1.0, 3.0), Vec2::new(1.0, 1.0), Vec2::new(3.0, 1.0),
+      Vec2::new(3.0, 2.0), Vec2::new(4.0, 2.0), Vec2::new(4.0, 4.0), Vec2::new(0.0, 4.0),
+      Vec2::new(0.0, 0.0)
+    ]);
+    assert!(kinds(&with_cut_in).is_empty());
+
+    let center = Vec2::new(1.0, 1.0);
+    let point = Vec2::new(2.0, 1.0);
+    let circle = Path::stroke(vec!(Box::new(Arc::new_with_fixed_center(point, point, center, CircularDirection::CCW))));
+    assert!(kinds(&circle).is_empty());
+  }
+
+  #[test]
+  fn broken_contours() {
+    let open = polyline(&[Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), Vec2::new(4.0, 4.0)]);
+    let issues = validate_contour(&open, &Tolerance::default());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RegionIssueKind::Unclosed);
+    assert_eq!(issues[0].location, Vec2::new(4.0, 4.0));
+
+    let bow_tie = polyline(&[
+      Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0), Vec2::new(2.0, 0.0), Vec2::new(0.0, 2.0), Vec2::new(0.0, 0.0)
+    ]);
+    let issues = validate_contour(&bow_tie, &Tolerance::default());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RegionIssueKind::SelfIntersection);
+    assert!((issues[0].location - Vec2::new(1.0, 1.0)).magnitude() < 1e-5);
+
+    let there_and_back = polyline(&[Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), Vec2::new(0.0, 0.0)]);
+    assert_eq!(kinds(&there_and_back), vec!(RegionIssueKind::Sliver));
+
+    // the cut-in goes back not the whole way it came
+    let bad_cut_in = polyline(&[
+      Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), Vec2::new(4.0, 2.0), Vec2::new(2.0, 2.0),
+      Vec2::new(3.0, 2.0), Vec2::new(3.0, 4.0), Vec2::new(0.0, 4.0), Vec2::new(0.0, 0.0)
+    ]);
+    assert!(kinds(&bad_cut_in).contains(&RegionIssueKind::InvalidCutIn));
+  }
+}
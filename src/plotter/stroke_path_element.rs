@@ -1,23 +1,27 @@
 extern crate nalgebra as na;
 use na::{ Rotation2, Vector2 };
 use std::fmt::Debug;
+use std::f32::consts::PI;
+use crate::parser::SourceSpan;
 use super::{
-  Arc,
   Line,
-  CircularDirection,
   Algebraic, 
-  RectDir, 
   AlgebraicPathElement, 
+  ApertureShape,
   Intersects, 
   PathType, 
   PathElement, 
+  Transform,
 };
 
 pub type Vec2 = Vector2<f32>;
 
+const ANGLE_EPSILON: f32 = 1e-5;
+const LENGTH_EPSILON: f32 = 1e-6;
+
 mod line_impl {
   use super::StrokePathElement;
-  use super::{ Line, Vec2 };
+  use super::{ Line, Vec2, ApertureShape, SourceSpan, Transform };
 
 
   impl StrokePathElement for Line {
@@ -25,9 +29,52 @@ mod line_impl {
       0.5 * (self.to + self.from)
     }
 
+    fn get_normal_in_central_point(&self) -> Vec2 {
+      self.normal
+    }
+
+    fn support_vertices(&self, shape: &ApertureShape, normal: &Vec2) -> (usize, usize) {
+      (shape.ccw_vertex_for(normal), shape.vertex_for(normal))
+    }
+
     fn has_point(&self, point: &Vec2) -> bool {
       self.is_on_segment(point)
     }
+
+    fn get_source(&self) -> Option<&SourceSpan> {
+      self.source.as_ref()
+    }
+
+    fn set_source(&mut self, source: Option<SourceSpan>) {
+      self.source = source;
+    }
+
+    fn parameter_of(&self, point: &Vec2) -> f32 {
+      let dir = self.to - self.from;
+      let length = dir.dot(&dir);
+      if length <= f32::EPSILON {
+        0.0
+      } else {
+        (point - self.from).dot(&dir) / length
+      }
+    }
+
+    fn translated(&self, shift: &Vec2) -> Box<dyn StrokePathElement> {
+      let copy = self.clone();
+      Box::new(Line {
+        from: self.from + shift,
+        to: self.to + shift,
+        ..copy
+      })
+    }
+
+    fn transformed(&self, transform: &Transform) -> Vec<Box<dyn StrokePathElement>> {
+      vec!(Box::new(Line {
+        source: self.source.clone(),
+        ..Line::new(transform.apply(&self.to), transform.apply(&self.from))
+      }))
+    }
+
     fn create_forward_with(&self, forward_start_point: Vec2, forward_end_point: Vec2) -> Box<dyn StrokePathElement> {
       let copy = self.clone();
       Box::new(Line {
@@ -41,7 +88,8 @@ mod line_impl {
         from: forward_start_point,
         to: forward_end_point,
         direction: - self.direction,
-        normal: - self.normal
+        normal: - self.normal,
+        source: self.source.clone()
       })
     }
   }
@@ -49,8 +97,8 @@ mod line_impl {
 
 mod arc_impl {
   use super::{PathElement, StrokePathElement };
-  use super::super::{ Arc, CircularDirection };
-  use super::Vec2;
+  use super::super::{ Arc, CircularDirection, Line, ARC_APPROXIMATION_STEP };
+  use super::{ Vec2, SourceSpan, Transform };
   use super::Rotation2;
 
   impl StrokePathElement for Arc {
@@ -61,266 +109,315 @@ mod arc_impl {
         CircularDirection::CCW => 1.0
       };
 
-      let dir = Rotation2::new(half_angle) * self.get_normal_in_start_point();
+      let dir = Rotation2::new(half_angle) * self.normal_in_start_point;
+
+      self.center + dir * self.get_radius()
+    }
+
+    fn get_normal_in_central_point(&self) -> Vec2 {
+      let half_angle = self.angle_length / 2.0 * match self.direction {
+        CircularDirection::CW => -1.0,
+        CircularDirection::CCW => 1.0
+      };
 
-      dir * self.get_radius()
+      Rotation2::new(half_angle) * self.get_normal_in_start_point()
     }
+
     fn has_point(&self, point: &Vec2) -> bool {
       self.is_on_arc(point)
     }
 
+    fn get_source(&self) -> Option<&SourceSpan> {
+      self.source.as_ref()
+    }
+
+    fn set_source(&mut self, source: Option<SourceSpan>) {
+      self.source = source;
+    }
+
+    fn parameter_of(&self, point: &Vec2) -> f32 {
+      self.sweep_to(point) / self.angle_length
+    }
+
+    fn translated(&self, shift: &Vec2) -> Box<dyn StrokePathElement> {
+      let copy = self.clone();
+      Box::new(Arc {
+        from: self.from + shift,
+        to: self.to + shift,
+        center: self.center + shift,
+        ..copy
+      })
+    }
+
+    // Similarity keeps the arc an arc, mirroring flips its direction.
+    // Any other transform makes an ellipse of it, which is approximated by lines.
+    fn transformed(&self, transform: &Transform) -> Vec<Box<dyn StrokePathElement>> {
+      if transform.is_similarity() {
+        let direction = if transform.is_mirroring() {
+          self.direction.clone().reverse()
+        } else {
+          self.direction.clone()
+        };
+        return vec!(Box::new(Arc {
+          source: self.source.clone(),
+          is_initially_single: self.is_initially_single,
+          ..Arc::new_with_fixed_center(
+            transform.apply(&self.to),
+            transform.apply(&self.from),
+            transform.apply(&self.center),
+            direction
+          )
+        }));
+      }
+      let count = (self.angle_length / ARC_APPROXIMATION_STEP).ceil().max(1.0) as usize;
+      let points: Vec<Vec2> = (0..=count)
+        .map(|ix| match ix {
+          0 => self.from,
+          ix if ix == count => self.to,
+          ix => self.point_at(self.angle_length * ix as f32 / count as f32)
+        })
+        .map(|point| transform.apply(&point))
+        .collect();
+      points.windows(2)
+        .map(|pair| {
+          let line: Box<dyn StrokePathElement> = Box::new(Line {
+            source: self.source.clone(),
+            ..Line::new(pair[1], pair[0])
+          });
+          line
+        })
+        .collect()
+    }
+
     fn create_forward_with(&self, forward_start_point: Vec2, forward_end_point: Vec2) -> Box<dyn StrokePathElement> {
 
-      Box::new(
-        Arc::new_with_fixed_center(
+      Box::new(Arc {
+        source: self.source.clone(),
+        ..Arc::new_with_fixed_center(
           forward_end_point, 
           forward_start_point, 
           self.center, 
           self.direction.clone()
-          ))
+          )
+      })
     }
     fn create_backward_with(&self, forward_start_point: Vec2, forward_end_point: Vec2) -> Box<dyn StrokePathElement> {
-      Box::new(
-        Arc::new_with_fixed_center(
+      Box::new(Arc {
+        source: self.source.clone(),
+        ..Arc::new_with_fixed_center(
           forward_end_point, 
           forward_start_point, 
           self.center, 
           self.direction.clone().reverse()
-          ))
+          )
+      })
     }
   }
 }
 
+fn nearest_intersection(one: &dyn StrokePathElement, two: &dyn StrokePathElement, near: &Vec2) -> Option<Vec2> {
+  one.get_intersector().intersects(two.get_intersector()).into_iter()
+    .min_by(|a, b| (a - near).magnitude().partial_cmp(&(b - near).magnitude()).unwrap())
+}
+
+// Offset, which is clipped by its neighbours down to a point, lies inside the stroke.
+// It is dropped, else the same start and end points would make a full circle of an arc.
+fn is_clipped_out(from: &Vec2, to: &Vec2, unclipped_from: &Vec2, unclipped_to: &Vec2) -> bool {
+  (to - from).magnitude() <= LENGTH_EPSILON && (unclipped_to - unclipped_from).magnitude() > LENGTH_EPSILON
+}
+
+fn forward_transition<A, B>(path_type: &PathType, element: &A, next: &B) -> Option<Vec<Box<dyn StrokePathElement>>>
+  where A: StrokePathElement + ?Sized, B: StrokePathElement + ?Sized {
+  let shape = path_type.aperture_shape();
+  let my_dir = element.get_direction_in_end_point();
+  let other_dir = next.get_direction_in_start_point();
+  let angle = Rotation2::rotation_between(&my_dir, &other_dir).angle();
+  if angle > ANGLE_EPSILON {
+    None
+  } else {
+    shape.walk_cw(
+      &element.get_end_point(),
+      element.forward_vertices(&shape).1,
+      &element.get_normal_in_end_poing(),
+      next.forward_vertices(&shape).0,
+      &next.get_normal_in_start_point(),
+      -angle
+    )
+  }
+}
+
+fn backward_transition<A, B>(path_type: &PathType, element: &A, prev: &B) -> Option<Vec<Box<dyn StrokePathElement>>>
+  where A: StrokePathElement + ?Sized, B: StrokePathElement + ?Sized {
+  let shape = path_type.aperture_shape();
+  let my_dir = element.get_direction_in_start_point();
+  let other_dir = prev.get_direction_in_end_point();
+  let angle = Rotation2::rotation_between(&other_dir, &my_dir).angle();
+  if angle < -ANGLE_EPSILON {
+    None
+  } else {
+    shape.walk_cw(
+      &element.get_start_point(),
+      element.backward_vertices(&shape).1,
+      &-element.get_normal_in_start_point(),
+      prev.backward_vertices(&shape).0,
+      &-prev.get_normal_in_end_poing(),
+      angle
+    )
+  }
+}
+
 pub trait StrokePathElement: Algebraic<AlgebraicPathElement> + PathElement + Intersects + Debug {
   fn has_point(&self, point: &Vec2) -> bool;
+  fn get_source(&self) -> Option<&SourceSpan>;
+  fn set_source(&mut self, source: Option<SourceSpan>);
   fn get_central_point(&self) -> Vec2;
+  fn get_normal_in_central_point(&self) -> Vec2;
+  fn parameter_of(&self, point: &Vec2) -> f32;
+  fn translated(&self, shift: &Vec2) -> Box<dyn StrokePathElement>;
+  fn transformed(&self, transform: &Transform) -> Vec<Box<dyn StrokePathElement>>;
+
+  fn reversed(&self) -> Box<dyn StrokePathElement> {
+    self.create_backward_with(self.get_end_point(), self.get_start_point())
+  }
+
+  fn length(&self) -> f32 {
+    match self.algebraic() {
+      AlgebraicPathElement::Line(line) => (line.to - line.from).magnitude(),
+      AlgebraicPathElement::Arc(arc) => arc.get_radius() * arc.angle_length
+    }
+  }
+
   fn split_by(&self, split_point: &Vec2) -> Vec<Box<dyn StrokePathElement>> {
     let one = self.create_forward_with(self.get_start_point(), *split_point);
     let two = self.create_forward_with(*split_point, self.get_end_point());
     vec!(one, two)
   }
 
-  fn get_forward_start_point(&self, path_type: &PathType) -> Vec2 {
-    self.get_start_point() + match path_type {
-      PathType::Circle(radius) => {
-         *radius * self.get_normal_in_start_point()
-      },
-      PathType::Rect(w,h) => {
-        let wh = Vec2::new(*w, *h);
-        let rect_dir = RectDir::from_dir(self.get_direction_in_start_point(), self.get_normal_in_start_point());
+  fn split_by_points(&self, points: &[Vec2]) -> Vec<Box<dyn StrokePathElement>> {
+    let mut points: Vec<(f32, Vec2)> = points.iter()
+      .map(|p| (self.parameter_of(p), *p))
+      .filter(|(t, _)| *t > 0.0 && *t < 1.0)
+      .collect();
+    points.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
 
-        rect_dir.start(&wh)
-      },
-      _ => unreachable!()
+    let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
+    let mut from = self.get_start_point();
+    for (_, point) in points.into_iter() {
+      if (point - from).magnitude() > f32::EPSILON {
+        result.push(self.create_forward_with(from, point));
+        from = point;
+      }
     }
+    result.push(self.create_forward_with(from, self.get_end_point()));
+    result
+  }
+
+  // Aperture vertices touching the offset traversed with `normal` on its left,
+  // in its first and last points.
+  fn support_vertices(&self, shape: &ApertureShape, normal: &Vec2) -> (usize, usize) {
+    let vertex = shape.vertex_for(normal);
+    (vertex, vertex)
+  }
+
+  fn forward_vertices(&self, shape: &ApertureShape) -> (usize, usize) {
+    self.support_vertices(shape, &self.get_normal_in_central_point())
+  }
+
+  fn backward_vertices(&self, shape: &ApertureShape) -> (usize, usize) {
+    self.support_vertices(shape, &-self.get_normal_in_central_point())
+  }
+
+  fn get_forward_start_point(&self, path_type: &PathType) -> Vec2 {
+    let shape = path_type.aperture_shape();
+    self.get_start_point() + shape.offset(self.forward_vertices(&shape).0, &self.get_normal_in_start_point())
   }
 
   fn get_forward_end_point(&self, path_type: &PathType) -> Vec2 {
-    self.get_end_point() + match path_type {
-      PathType::Circle(radius) => {
-        *radius * self.get_normal_in_end_poing()
-      },
-      PathType::Rect(w,h) => {
-        let wh = Vec2::new(*w, *h);
-        let rect_dir = RectDir::from_dir(self.get_direction_in_end_point(), self.get_normal_in_end_poing());
-        rect_dir.end(&wh)
-      },
-      _ => unreachable!()
-    }
+    let shape = path_type.aperture_shape();
+    self.get_end_point() + shape.offset(self.forward_vertices(&shape).1, &self.get_normal_in_end_poing())
   }
 
   fn get_backward_start_point(&self, path_type: &PathType) -> Vec2 {
-    self.get_end_point() + match path_type {
-      PathType::Circle(radius) => {
-        *radius * -self.get_normal_in_end_poing()
-      },
-      PathType::Rect(w,h) => {
-        let wh = Vec2::new(*w, *h);
-        let rect_dir = RectDir::from_dir(-self.get_direction_in_end_point(), -self.get_normal_in_end_poing());
-        rect_dir.start(&wh)
-      },
-      _ => unreachable!()
-    }
+    let shape = path_type.aperture_shape();
+    self.get_end_point() + shape.offset(self.backward_vertices(&shape).0, &-self.get_normal_in_end_poing())
   }
 
   fn get_backward_end_point(&self, path_type: &PathType) -> Vec2 {
-    self.get_start_point() + match path_type {
-      PathType::Circle(radius) => {
-        *radius * -self.get_normal_in_start_point()
-      },
+    let shape = path_type.aperture_shape();
+    self.get_start_point() + shape.offset(self.backward_vertices(&shape).1, &-self.get_normal_in_start_point())
+  }
 
-      PathType::Rect(w,h) => {
-        let wh = Vec2::new(*w, *h);
-        let rect_dir = RectDir::from_dir(-self.get_direction_in_start_point(), -self.get_normal_in_start_point());
-        rect_dir.end(&wh)
-      },
-      _ => unreachable!()
-    }
+  fn create_forward_offset(&self, path_type: &PathType, from: Vec2, to: Vec2) -> Box<dyn StrokePathElement> {
+    let shape = path_type.aperture_shape();
+    let shift = shape.vertices()[self.forward_vertices(&shape).0];
+    self.translated(&shift).create_forward_with(from, to)
   }
 
+  fn create_backward_offset(&self, path_type: &PathType, from: Vec2, to: Vec2) -> Box<dyn StrokePathElement> {
+    let shape = path_type.aperture_shape();
+    let shift = shape.vertices()[self.backward_vertices(&shape).0];
+    self.translated(&shift).create_backward_with(from, to)
+  }
 
   fn create_ending_cap(&self, path_type: &PathType) -> Vec<Box<dyn StrokePathElement>> {
-    match path_type {
-      PathType::Circle(_) => {
-        let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
-        let from = self.get_forward_end_point(path_type);
-        let to = self.get_backward_start_point(path_type);
-        let center = self.get_end_point();
-        result.push(Box::new(Arc::new_with_fixed_center(
-            to,
-            from,
-            center,
-            CircularDirection::CW
-            )));
-        result
-      },
-      PathType::Rect(w, h) => {
-        let wh = Vec2::new(*w, *h);
-        let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
-        let back_start_rect_dir = RectDir::from_dir(-self.get_direction_in_end_point(), -self.get_normal_in_end_poing());
-        let forw_end_rect_dir = RectDir::from_dir(self.get_direction_in_end_point(), self.get_normal_in_end_poing());
-        if forw_end_rect_dir.is_ortho() {
-          let from = forw_end_rect_dir.end(&wh) + self.get_end_point();
-          let to = back_start_rect_dir.start(&wh) + self.get_end_point();
-          result.push(Box::new(Line::new(to, from)));
-          result
-        } else {
-          let from = forw_end_rect_dir.end(&wh) + self.get_end_point();
-          let middle = back_start_rect_dir.opposite(&wh) + self.get_end_point();
-          let to = back_start_rect_dir.start(&wh) + self.get_end_point();
-          result.push(Box::new(Line::new(middle, from)));
-          result.push(Box::new(Line::new(to, middle)));
-          result
-        }
-      },
-      _ => unreachable!()
-    }
+    let shape = path_type.aperture_shape();
+    let normal = self.get_normal_in_end_poing();
+    shape.walk_cw(
+      &self.get_end_point(),
+      self.forward_vertices(&shape).1,
+      &normal,
+      self.backward_vertices(&shape).0,
+      &-normal,
+      PI
+    ).unwrap_or_else(|| vec!(Box::new(Line::new(
+      self.get_backward_start_point(path_type),
+      self.get_forward_end_point(path_type)
+    ))))
   }
 
   fn create_starting_cap(&self, path_type: &PathType) -> Vec<Box<dyn StrokePathElement>> {
-    match path_type {
-      PathType::Circle(_) => {
-        let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
-        let from = self.get_backward_end_point(path_type);
-        let to = self.get_forward_start_point(path_type);
-        let center = self.get_start_point();
-        result.push(Box::new(Arc::new_with_fixed_center(
-            to,
-            from,
-            center,
-            CircularDirection::CW
-            )));
-        result
-      },
-      PathType::Rect(w, h) => {
-        let wh = Vec2::new(*w, *h);
-        let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
-        let back_start_rect_dir = RectDir::from_dir(-self.get_direction_in_start_point(), -self.get_normal_in_start_point());
-        let forw_end_rect_dir = RectDir::from_dir(self.get_direction_in_start_point(), self.get_normal_in_start_point());
-        if back_start_rect_dir.is_ortho() {
-          let from = back_start_rect_dir.end(&wh) + self.get_start_point();
-          let to = forw_end_rect_dir.start(&wh) + self.get_start_point();
-          result.push(Box::new(Line::new(to, from)));
-          result
-        } else {
-          let from = back_start_rect_dir.end(&wh) + self.get_start_point();
-          let middle = forw_end_rect_dir.opposite(&wh) + self.get_start_point();
-          let to = forw_end_rect_dir.start(&wh) + self.get_start_point();
-          result.push(Box::new(Line::new(middle, from)));
-          result.push(Box::new(Line::new(to, middle)));
-          result
-        }
-      },
-      _ => unreachable!()
-    }
+    let shape = path_type.aperture_shape();
+    let normal = self.get_normal_in_start_point();
+    shape.walk_cw(
+      &self.get_start_point(),
+      self.backward_vertices(&shape).1,
+      &-normal,
+      self.forward_vertices(&shape).0,
+      &normal,
+      PI
+    ).unwrap_or_else(|| vec!(Box::new(Line::new(
+      self.get_forward_start_point(path_type),
+      self.get_backward_end_point(path_type)
+    ))))
   }
 
   fn create_forward_with(&self, forward_start_point: Vec2, forward_end_point: Vec2) -> Box<dyn StrokePathElement>;
   fn create_backward_with(&self, forward_start_point: Vec2, forward_end_point: Vec2) -> Box<dyn StrokePathElement>;
 
   fn has_forward_transition(&self, path_type: &PathType, next: &dyn StrokePathElement) -> bool {
-    match path_type {
-      PathType::Rect(_,_) => false,
-      PathType::Circle(_) => {
-        let my_dir = self.get_direction_in_end_point();
-        let other_dir = next.get_direction_in_start_point();
-        let angle = Rotation2::rotation_between(&my_dir, &other_dir).angle();
-        angle < 0.0
-      },
-      _ => unreachable!()
-    }
+    forward_transition(path_type, self, next).is_some()
   }
 
   fn has_backward_transition(&self, path_type: &PathType, prev: &dyn StrokePathElement) -> bool {
-    match path_type {
-      PathType::Rect(_,_) => false,
-      PathType::Circle(_) => {
-        let my_dir = self.get_direction_in_start_point();
-        let other_dir = prev.get_direction_in_end_point();
-        let angle = Rotation2::rotation_between(&other_dir, &my_dir).angle();
-        angle > 0.0
-      },
-      _ => unreachable!()
-    }
+    backward_transition(path_type, self, prev).is_some()
   }
 
   fn create_forward_transition(&self, path_type: &PathType, next: &dyn StrokePathElement) 
-    -> Box<dyn StrokePathElement> {
-    match path_type {
-      PathType::Circle(_) => {
-        let center = self.get_end_point();
-        let from = self.get_forward_end_point(&path_type);
-        let to = next.get_forward_start_point(&path_type);
-
-        Box::new(Arc::new_with_fixed_center(to, from, center, CircularDirection::CW))
-      },
-      PathType::Rect(_w, _h) => {
-        unreachable!("Cannot make transition for rect path")
-      },
-      _ => unreachable!()
-    }
+    -> Vec<Box<dyn StrokePathElement>> {
+    forward_transition(path_type, self, next).unwrap_or_default()
   }
+
   fn create_backward_transition(&self, path_type: &PathType, prev: &dyn StrokePathElement) 
-    -> Box<dyn StrokePathElement> {
-    match path_type {
-      PathType::Circle(_) => {
-        let center = self.get_start_point();
-        let from = self.get_backward_end_point(&path_type);
-        let to = prev.get_backward_start_point(&path_type);
-
-        Box::new(Arc::new_with_fixed_center(to, from, center, CircularDirection::CW))
-      },
-      PathType::Rect(_w, _h) => {
-        unreachable!("Cannot make transition for rect path")
-      },
-      _ => unreachable!()
-    }
+    -> Vec<Box<dyn StrokePathElement>> {
+    backward_transition(path_type, self, prev).unwrap_or_default()
   }
 
   fn has_backward_transition_with_next(&self, path_type: &PathType, next: &dyn StrokePathElement) -> bool {
-    match path_type {
-      PathType::Rect(_,_) => false,
-      PathType::Circle(_) => {
-        let my_dir = self.get_direction_in_end_point();
-        let other_dir = next.get_direction_in_start_point();
-        let angle = Rotation2::rotation_between(&other_dir, &my_dir).angle();
-        angle < 0.0
-      },
-      _ => unreachable!()
-    }
+    backward_transition(path_type, next, self).is_some()
   }
 
   fn has_forward_transition_with_prev(&self, path_type: &PathType, prev: &dyn StrokePathElement) -> bool {
-    match path_type {
-      PathType::Rect(_,_) => false,
-      PathType::Circle(_) => {
-        let my_dir = self.get_direction_in_start_point();
-        let other_dir = prev.get_direction_in_end_point();
-        let angle = Rotation2::rotation_between(&other_dir, &my_dir).angle();
-        angle < 0.0
-      },
-      _ => unreachable!()
-    }
+    forward_transition(path_type, prev, self).is_some()
   }
 
   fn forward(
@@ -331,31 +428,32 @@ pub trait StrokePathElement: Algebraic<AlgebraicPathElement> + PathElement + Int
     is_path_locked: bool
   ) -> Vec<Box<dyn StrokePathElement>> {
     let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
-    let mut forward_start_point = self.get_forward_start_point(&path_type);
-    let mut forward_end_point = self.get_forward_end_point(&path_type);
+    let mut forward_start_point = self.get_forward_start_point(path_type);
+    let mut forward_end_point = self.get_forward_end_point(path_type);
     let mut needs_start_cap = false;
     let mut needs_end_cap = false;
-    let mut to_for_transition: Option<&dyn StrokePathElement> = None;
+    let mut transition: Vec<Box<dyn StrokePathElement>> = Vec::new();
     match prev {
       None => {
         needs_start_cap = true;
       },
       Some(element) => {
-        if !self.has_forward_transition_with_prev(&path_type, element) {
-          let line_one = self.create_forward_with(
-            self.get_forward_start_point(&path_type), 
-            self.get_forward_end_point(&path_type)
+        if !self.has_forward_transition_with_prev(path_type, element) {
+          let line_one = self.create_forward_offset(
+            path_type,
+            self.get_forward_start_point(path_type), 
+            self.get_forward_end_point(path_type)
             );
 
-          let line_two = element.create_forward_with(
-            element.get_forward_start_point(&path_type),
-            element.get_forward_end_point(&path_type)
+          let line_two = element.create_forward_offset(
+            path_type,
+            element.get_forward_start_point(path_type),
+            element.get_forward_end_point(path_type)
             );
 
-          if let Some(pt) = line_two.get_intersector().intersects(line_one.get_intersector()).pop() {
+          let near = 0.5 * (element.get_forward_end_point(path_type) + self.get_forward_start_point(path_type));
+          if let Some(pt) = nearest_intersection(line_two.as_ref(), line_one.as_ref(), &near) {
             forward_start_point = pt;
-          }else {
-            panic!("Cannot find intersecttion, but we should!");
           }
         }
       }
@@ -365,47 +463,51 @@ pub trait StrokePathElement: Algebraic<AlgebraicPathElement> + PathElement + Int
         needs_end_cap = true;
       },
       Some(element) => {
-        if self.has_forward_transition(&path_type, element) {
-          to_for_transition.replace(element);
+        if self.has_forward_transition(path_type, element) {
+          transition = self.create_forward_transition(path_type, element);
         }else {
-          let line_one = self.create_forward_with(
-            self.get_forward_start_point(&path_type), 
-            self.get_forward_end_point(&path_type)
+          let line_one = self.create_forward_offset(
+            path_type,
+            self.get_forward_start_point(path_type), 
+            self.get_forward_end_point(path_type)
             );
 
-          let line_two = element.create_forward_with(
-            element.get_forward_start_point(&path_type),
-            element.get_forward_end_point(&path_type)
+          let line_two = element.create_forward_offset(
+            path_type,
+            element.get_forward_start_point(path_type),
+            element.get_forward_end_point(path_type)
             );
 
-          if let Some(pt) = line_one.get_intersector().intersects(line_two.get_intersector()).pop() {
+          let near = 0.5 * (self.get_forward_end_point(path_type) + element.get_forward_start_point(path_type));
+          if let Some(pt) = nearest_intersection(line_one.as_ref(), line_two.as_ref(), &near) {
             forward_end_point = pt;
-          }else {
-            panic!("Cannot find intersecttion, but we should!");
+          } else {
+            transition.push(Box::new(Line::new(element.get_forward_start_point(path_type), forward_end_point)));
           }
-
         }
       }
     }
 
     if needs_start_cap && !is_path_locked{
-      for p in self.create_starting_cap(&path_type).into_iter() {
+      for p in self.create_starting_cap(path_type).into_iter() {
         result.push(p);
       }
     }
 
-    result.push(self.create_forward_with(forward_start_point, forward_end_point));
-
-    if let Some(next_element) = to_for_transition {
-      result.push(self.create_forward_transition(&path_type, next_element));
+    if !is_clipped_out(
+      &forward_start_point,
+      &forward_end_point,
+      &self.get_forward_start_point(path_type),
+      &self.get_forward_end_point(path_type)
+    ) {
+      result.push(self.create_forward_offset(path_type, forward_start_point, forward_end_point));
     }
+    result.extend(transition);
 
     if needs_end_cap && !is_path_locked{
-      result.extend(self.create_ending_cap(&path_type));
+      result.extend(self.create_ending_cap(path_type));
     }
 
-    println!("forward {:?}", result);
-
     result
   }
 
@@ -416,63 +518,76 @@ pub trait StrokePathElement: Algebraic<AlgebraicPathElement> + PathElement + Int
     next: Option<&dyn StrokePathElement>, 
   ) -> Vec<Box<dyn StrokePathElement>> {
     let mut result: Vec<Box<dyn StrokePathElement>> = Vec::new();
-    let mut backward_start_point = self.get_backward_start_point(&path_type);
-    let mut backward_end_point = self.get_backward_end_point(&path_type);
-    let mut to_for_transition: Option<&dyn StrokePathElement> = None;
+    let mut backward_start_point = self.get_backward_start_point(path_type);
+    let mut backward_end_point = self.get_backward_end_point(path_type);
+    let mut transition: Vec<Box<dyn StrokePathElement>> = Vec::new();
+    let mut connection: Option<Box<dyn StrokePathElement>> = None;
     match prev {
       None => (),
       Some(element) => {
-        if self.has_backward_transition(&path_type, element) {
-          to_for_transition.replace(element);
+        if self.has_backward_transition(path_type, element) {
+          transition = self.create_backward_transition(path_type, element);
         }else {
-          let line_one = self.create_backward_with(
-            self.get_backward_start_point(&path_type), 
-            self.get_backward_end_point(&path_type)
+          let line_one = self.create_backward_offset(
+            path_type,
+            self.get_backward_start_point(path_type), 
+            self.get_backward_end_point(path_type)
           );
-          let line_two = element.create_backward_with(
-            element.get_backward_start_point(&path_type), 
-            element.get_backward_end_point(&path_type)
+          let line_two = element.create_backward_offset(
+            path_type,
+            element.get_backward_start_point(path_type), 
+            element.get_backward_end_point(path_type)
           );
 
-
-          if let Some(pt) = line_one.get_intersector().intersects(line_two.get_intersector()).pop() {
+          let near = 0.5 * (self.get_backward_end_point(path_type) + element.get_backward_start_point(path_type));
+          if let Some(pt) = nearest_intersection(line_one.as_ref(), line_two.as_ref(), &near) {
             backward_end_point = pt;
-          }else {
-            panic!("Cannot find intersecttion, but we should!");
           }
-
         }
       }
     }
 
     match next {
-      None => (),
-      Some(element) => {
-        if !self.has_backward_transition_with_next(&path_type, element) {
-          let line_one = self.create_backward_with(
-            self.get_backward_start_point(&path_type), 
-            self.get_backward_end_point(&path_type)
-          );
-          let line_two = element.create_backward_with(
-            element.get_backward_start_point(&path_type), 
-            element.get_backward_end_point(&path_type)
-          );
-
-          if let Some(pt) = line_two.get_intersector().intersects(line_one.get_intersector()).pop() {
-            backward_start_point = pt;
-          }else {
-            panic!("Cannot find intersecttion, but we should!");
-          }
+      Some(element) if !self.has_backward_transition_with_next(path_type, element) => {
+        let line_one = self.create_backward_offset(
+          path_type,
+          self.get_backward_start_point(path_type), 
+          self.get_backward_end_point(path_type)
+        );
+        let line_two = element.create_backward_offset(
+          path_type,
+          element.get_backward_start_point(path_type), 
+          element.get_backward_end_point(path_type)
+        );
+
+        let near = 0.5 * (self.get_backward_start_point(path_type) + element.get_backward_end_point(path_type));
+        if let Some(pt) = nearest_intersection(line_two.as_ref(), line_one.as_ref(), &near) {
+          backward_start_point = pt;
+        } else {
+          connection.replace(Box::new(Line::new(
+            self.get_backward_start_point(path_type),
+            element.get_backward_end_point(path_type)
+          )));
         }
-      }
+      },
+      _ => ()
     }
 
+    // whole backward side is reversed later, so keep transition elements in reverse order
+    result.extend(transition.into_iter().rev());
 
-    if let Some(prev_node) = to_for_transition {
-      result.push(self.create_backward_transition(&path_type, prev_node));
-    };
+    if !is_clipped_out(
+      &backward_start_point,
+      &backward_end_point,
+      &self.get_backward_start_point(path_type),
+      &self.get_backward_end_point(path_type)
+    ) {
+      result.push(self.create_backward_offset(path_type, backward_start_point, backward_end_point));
+    }
 
-    result.push(self.create_backward_with(backward_start_point, backward_end_point));
+    if let Some(element) = connection {
+      result.push(element);
+    }
 
     result
   }
@@ -0,0 +1,86 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use super::{ AlgebraicPathElement, StrokePathElement, Tolerance };
+use crate::svg::BoundingBoxTrait;
+
+type Vec2 = Vector2<f32>;
+
+pub(crate) fn bounds(element: &dyn StrokePathElement, tolerance: &Tolerance) -> (Vec2, Vec2) {
+  let bounding_box = match element.algebraic() {
+    AlgebraicPathElement::Line(line) => line.get_bounding_box(),
+    AlgebraicPathElement::Arc(arc) => arc.get_bounding_box()
+  };
+  let margin = Vec2::new(tolerance.distance, tolerance.distance);
+  (bounding_box.min - margin, bounding_box.max + margin)
+}
+
+// Pairs of elements with overlapping bounding boxes. Elements are swept
+// along x, so only the ones with intersecting x-ranges are ever compared.
+pub fn candidate_pairs(elements: &[&dyn StrokePathElement], tolerance: &Tolerance) -> Vec<(usize, usize)> {
+  let boxes: Vec<(Vec2, Vec2)> = elements.iter().map(|el| bounds(*el, tolerance)).collect();
+  let mut order: Vec<usize> = (0..elements.len()).collect();
+  order.sort_by(|a, b| boxes[*a].0.x.partial_cmp(&boxes[*b].0.x).unwrap());
+
+  let mut pairs = Vec::new();
+  let mut active: Vec<usize> = Vec::new();
+  for ix in order.into_iter() {
+    let (min, max) = boxes[ix];
+    active.retain(|other| boxes[*other].1.x >= min.x);
+    for other in active.iter() {
+      let (other_min, other_max) = boxes[*other];
+      if other_min.y <= max.y && min.y <= other_max.y {
+        pairs.push(((*other).min(ix), (*other).max(ix)));
+      }
+    }
+    active.push(ix);
+  }
+  pairs
+}
+
+// Points, where each element crosses or touches any other element,
+// including both ends of their common pieces.
+pub fn intersection_points(elements: &[&dyn StrokePathElement], tolerance: &Tolerance) -> Vec<Vec<Vec2>> {
+  let mut points: Vec<Vec<Vec2>> = vec!(Vec::new(); elements.len());
+  for (i, j) in candidate_pairs(elements, tolerance).into_iter() {
+    let contacts = elements[i].get_intersector().contacts(elements[j].get_intersector(), tolerance.distance);
+    for point in contacts.iter().flat_map(|contact| contact.points()) {
+      points[i].push(point);
+      points[j].push(point);
+    }
+  }
+  points
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::{ Arc, Line, CircularDirection };
+
+  #[test]
+  fn grid_intersections() {
+    let size = 50;
+    let mut elements: Vec<Box<dyn StrokePathElement>> = Vec::new();
+    for i in 0..size {
+      let c = i as f32 + 0.5;
+      elements.push(Box::new(Line::new(Vec2::new(size as f32, c), Vec2::new(0.0, c))));
+      elements.push(Box::new(Line::new(Vec2::new(c, size as f32), Vec2::new(c, 0.0))));
+    }
+    let refs: Vec<&dyn StrokePathElement> = elements.iter().map(|el| el.as_ref()).collect();
+    let points = intersection_points(&refs, &Tolerance::default());
+    assert!(points.iter().all(|p| p.len() == size));
+  }
+
+  #[test]
+  fn arc_bounds_are_not_just_endpoints() {
+    let arc = Arc::new_with_fixed_center(
+      Vec2::new(-1.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      CircularDirection::CCW
+    );
+    let line = Line::new(Vec2::new(0.5, 2.0), Vec2::new(0.5, 0.5));
+    let refs: Vec<&dyn StrokePathElement> = vec!(&arc, &line);
+    assert_eq!(candidate_pairs(&refs, &Tolerance::default()), vec!((0, 1)));
+    assert_eq!(intersection_points(&refs, &Tolerance::default())[0].len(), 1);
+  }
+}
@@ -0,0 +1,118 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use crate::parser::FormatSpecification;
+
+type Vec2 = Vector2<f32>;
+
+pub const DEFAULT_TOLERANCE: f32 = 1e-5;
+
+// Computed points may be off by that many steps of f32 resolution.
+const RESOLUTION_STEPS: f32 = 4.0;
+
+// Distance, below which two points are considered the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+  pub distance: f32
+}
+
+impl Tolerance {
+  pub fn new(distance: f32) -> Self {
+    Tolerance { distance }
+  }
+
+  // Half of the smallest coordinate step the file is able to express,
+  // but not finer than f32 allows for usual board sizes.
+  pub fn from_format(format: &FormatSpecification) -> Self {
+    let decimals = format.x.rational.max(format.y.rational) as i32;
+    Tolerance::new((0.5 * 10_f32.powi(-decimals)).max(DEFAULT_TOLERANCE))
+  }
+
+  // f32 keeps coordinates as large as `extent` only to its relative precision,
+  // so points that far from origin are not told apart finer than that.
+  pub fn covering(&self, extent: f32) -> Self {
+    Tolerance::new(self.distance.max(RESOLUTION_STEPS * f32::EPSILON * extent.abs()))
+  }
+
+  pub fn is_zero(&self, length: f32) -> bool {
+    length.abs() <= self.distance
+  }
+
+  // Angle, by which a point `radius` away from the pivot turns no further
+  // than tolerance.
+  pub fn angle(&self, radius: f32) -> f32 {
+    self.distance / radius
+  }
+
+  pub fn is_same_point(&self, one: &Vec2, two: &Vec2) -> bool {
+    (one - two).magnitude() <= self.distance
+  }
+
+  // Cell of the grid with tolerance-sized step, the point belongs to.
+  pub fn grid_key(&self, point: &Vec2) -> (i64, i64) {
+    (
+      (point.x / self.distance).round() as i64,
+      (point.y / self.distance).round() as i64
+    )
+  }
+
+  // Cells around the one of the point. A point within tolerance from it
+  // may be rounded to any of them, but never further.
+  pub fn nearby_keys(&self, point: &Vec2) -> Vec<(i64, i64)> {
+    let (x, y) = self.grid_key(point);
+    (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (x + dx, y + dy))).collect()
+  }
+
+  pub fn snap(&self, point: &Vec2) -> Vec2 {
+    let (x, y) = self.grid_key(point);
+    Vec2::new(x as f32 * self.distance, y as f32 * self.distance)
+  }
+}
+
+impl Default for Tolerance {
+  fn default() -> Self {
+    Tolerance::new(DEFAULT_TOLERANCE)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::NumberSpec;
+
+  #[test]
+  fn tolerance_from_format() {
+    let format = FormatSpecification {
+      x: NumberSpec { integer: 3, rational: 3 },
+      y: NumberSpec { integer: 3, rational: 3 }
+    };
+    assert_eq!(Tolerance::from_format(&format), Tolerance::new(0.0005));
+
+    let format = FormatSpecification {
+      x: NumberSpec { integer: 3, rational: 6 },
+      y: NumberSpec { integer: 3, rational: 6 }
+    };
+    assert_eq!(Tolerance::from_format(&format), Tolerance::default());
+  }
+
+  #[test]
+  fn tolerance_grows_with_coordinates() {
+    let tolerance = Tolerance::default();
+    assert_eq!(tolerance.covering(1.0), tolerance);
+    let far = tolerance.covering(500.0);
+    // next representable coordinate is within it
+    let x = 499.7_f32;
+    let next = f32::from_bits(x.to_bits() + 1);
+    assert!(far.is_same_point(&Vec2::new(x, 0.0), &Vec2::new(next, 0.0)));
+    assert!(next - x > DEFAULT_TOLERANCE);
+  }
+
+  #[test]
+  fn points_of_one_cell_are_snapped_together() {
+    let tolerance = Tolerance::new(0.001);
+    let one = Vec2::new(312.5, 120.0);
+    let two = Vec2::new(312.5002, 119.9999);
+    assert!(tolerance.is_same_point(&one, &two));
+    assert_eq!(tolerance.grid_key(&one), tolerance.grid_key(&two));
+    assert_eq!(tolerance.snap(&one), tolerance.snap(&two));
+  }
+}
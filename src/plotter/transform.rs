@@ -0,0 +1,167 @@
+extern crate nalgebra as na;
+use na::{ Matrix2, Vector2 };
+
+type Vec2 = Vector2<f32>;
+
+// Arcs, which cannot stay arcs, are replaced by lines through points
+// taken this often along them.
+pub const ARC_APPROXIMATION_STEP: f32 = std::f32::consts::PI / 32.0;
+
+// Affine transform of the plane: point is multiplied by matrix, then shifted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+  pub matrix: Matrix2<f32>,
+  pub shift: Vec2
+}
+
+impl Transform {
+  pub fn identity() -> Self {
+    Transform {
+      matrix: Matrix2::identity(),
+      shift: Vec2::zeros()
+    }
+  }
+
+  pub fn translate(shift: Vec2) -> Self {
+    Transform { shift, ..Transform::identity() }
+  }
+
+  // Counterclockwise rotation around the origin.
+  pub fn rotate(angle: f32) -> Self {
+    let (sin, cos) = angle.sin_cos();
+    Transform {
+      matrix: Matrix2::new(cos, -sin, sin, cos),
+      shift: Vec2::zeros()
+    }
+  }
+
+  pub fn rotate_around(center: Vec2, angle: f32) -> Self {
+    Transform::translate(-center)
+      .then(&Transform::rotate(angle))
+      .then(&Transform::translate(center))
+  }
+
+  // Mirroring the way LM command does it: `x` flips x coordinates,
+  // `y` flips y coordinates.
+  pub fn mirror(x: bool, y: bool) -> Self {
+    Transform::scale_xy(
+      if x { -1.0 } else { 1.0 },
+      if y { -1.0 } else { 1.0 }
+    )
+  }
+
+  pub fn scale(factor: f32) -> Self {
+    Transform::scale_xy(factor, factor)
+  }
+
+  pub fn scale_xy(x: f32, y: f32) -> Self {
+    Transform {
+      matrix: Matrix2::new(x, 0.0, 0.0, y),
+      shift: Vec2::zeros()
+    }
+  }
+
+  // Transform, which applies this one first and `next` after it.
+  pub fn then(&self, next: &Transform) -> Self {
+    Transform {
+      matrix: next.matrix * self.matrix,
+      shift: next.matrix * self.shift + next.shift
+    }
+  }
+
+  pub fn apply(&self, point: &Vec2) -> Vec2 {
+    self.matrix * point + self.shift
+  }
+
+  pub fn apply_vector(&self, vector: &Vec2) -> Vec2 {
+    self.matrix * vector
+  }
+
+  pub fn is_mirroring(&self) -> bool {
+    self.matrix.determinant() < 0.0
+  }
+
+  // Circles stay circles: the matrix is rotation, maybe mirrored, times uniform scale.
+  pub fn is_similarity(&self) -> bool {
+    let x = self.matrix.column(0);
+    let y = self.matrix.column(1);
+    let scale = x.norm_squared().max(y.norm_squared());
+    let limit = scale * 1e-5;
+    (x.norm_squared() - y.norm_squared()).abs() <= limit && x.dot(&y).abs() <= limit
+  }
+
+  // How many times lengths are changed by similarity transform.
+  pub fn scale_factor(&self) -> f32 {
+    self.matrix.determinant().abs().sqrt()
+  }
+}
+
+impl Default for Transform {
+  fn default() -> Self {
+    Transform::identity()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::{ AlgebraicPathElement, Arc, CircularDirection, StrokePathElement };
+  use std::f32::consts::PI;
+
+  fn quarter() -> Arc {
+    Arc::new_with_fixed_center(
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      CircularDirection::CCW
+    )
+  }
+
+  #[test]
+  fn composition_order() {
+    let transform = Transform::translate(Vec2::new(1.0, 0.0)).then(&Transform::rotate(PI / 2.0));
+    let point = transform.apply(&Vec2::new(1.0, 0.0));
+    assert!((point - Vec2::new(0.0, 2.0)).magnitude() < 1e-6);
+
+    let around = Transform::rotate_around(Vec2::new(1.0, 1.0), PI);
+    assert!((around.apply(&Vec2::new(2.0, 1.0)) - Vec2::new(0.0, 1.0)).magnitude() < 1e-6);
+  }
+
+  #[test]
+  fn kinds_of_transforms() {
+    assert!(Transform::rotate(0.3).then(&Transform::scale(2.0)).is_similarity());
+    assert!(Transform::mirror(true, false).is_similarity());
+    assert!(Transform::mirror(true, false).is_mirroring());
+    assert!(!Transform::mirror(true, true).is_mirroring());
+    assert!(!Transform::scale_xy(1.0, 2.0).is_similarity());
+    assert!((Transform::scale(3.0).then(&Transform::rotate(1.0)).scale_factor() - 3.0).abs() < 1e-5);
+  }
+
+  #[test]
+  fn arcs_stay_arcs_under_similarity() {
+    let transform = Transform::mirror(true, false).then(&Transform::translate(Vec2::new(5.0, 0.0)));
+    let result = quarter().transformed(&transform);
+    assert_eq!(result.len(), 1);
+    match result[0].algebraic() {
+      AlgebraicPathElement::Arc(arc) => {
+        assert_eq!(arc.direction, CircularDirection::CW);
+        assert_eq!(arc.center, Vec2::new(5.0, 0.0));
+        assert_eq!(arc.from, Vec2::new(4.0, 0.0));
+        assert!((arc.angle_length - PI / 2.0).abs() < 1e-5);
+      },
+      _ => panic!("arc expected")
+    }
+  }
+
+  #[test]
+  fn arcs_become_lines_under_stretch() {
+    let result = quarter().transformed(&Transform::scale_xy(2.0, 1.0));
+    assert_eq!(result.len(), 16);
+    assert_eq!(result[0].get_start_point(), Vec2::new(2.0, 0.0));
+    assert_eq!(result[15].get_end_point(), Vec2::new(0.0, 1.0));
+    for el in result.iter() {
+      let point = el.get_end_point();
+      assert!(((point.x / 2.0).powi(2) + point.y.powi(2) - 1.0).abs() < 1e-5);
+    }
+  }
+}
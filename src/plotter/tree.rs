@@ -36,9 +36,40 @@ impl<T>  Tree<T> {
     self.children.extend(sub_forest)
   }
 
+  pub fn children(&self) -> &Forest<T> {
+    &self.children
+  }
+
   pub fn forest_mut(&mut self) -> &mut Forest<T> {
     &mut self.children
   }
+
+  // Items in pre-order with their depth, the root is at zero depth.
+  pub fn walk(&self) -> TreeWalk<'_, T> {
+    TreeWalk { stack: vec!((0, self)) }
+  }
+
+  // Tree of the same shape with every item mapped.
+  pub fn map<U, F: Fn(&T) -> U>(&self, f: &F) -> Tree<U> {
+    Tree {
+      data: f(&self.data),
+      children: self.children.iter().map(|child| Box::new(child.map(f))).collect()
+    }
+  }
+}
+
+pub struct TreeWalk<'a, T> {
+  stack: Vec<(usize, &'a Tree<T>)>
+}
+
+impl<'a, T> Iterator for TreeWalk<'a, T> {
+  type Item = (usize, &'a T);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (depth, tree) = self.stack.pop()?;
+    self.stack.extend(tree.children.iter().rev().map(|child| (depth + 1, child.as_ref())));
+    Some((depth, &tree.data))
+  }
 }
 
 impl<T> IntoIterator for Tree<T> {
@@ -72,4 +103,18 @@ impl<T> Div for Tree<T> {
   }
 }
 
-
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn walk_is_pre_order() {
+    let mut inner = tr(2);
+    inner.push(3);
+    let mut tree = tr(1);
+    tree /= inner;
+    tree.push(4);
+    let walked: Vec<(usize, i32)> = tree.walk().map(|(depth, item)| (depth, *item)).collect();
+    assert_eq!(walked, vec!((0, 1), (1, 2), (2, 3), (1, 4)));
+  }
+}
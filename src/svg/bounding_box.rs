@@ -19,7 +19,9 @@ impl BoundingBoxTrait for Line {
 }
 impl BoundingBoxTrait for Arc {
   fn get_bounding_box(&self) -> bounding_box_struct::BoundingBox {
-    let mut bb = BoundingBox::default();
+    let mut bb = BoundingBox::default()
+      .add(self.from)
+      .add(self.to);
     for a in &[0.0, 0.25, 0.5, 0.75] {
       let a = 2.0 * PI * a ;
       let radius = (self.to - self.center).magnitude();
@@ -117,3 +119,25 @@ mod bounding_box_struct {
   }
 }
 
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::plotter::CircularDirection;
+
+  #[test]
+  fn arc_box_has_its_ends() {
+    // quarter from 30 to 60 degrees has no extreme point of the circle
+    let center = Vec2::new(1.0, 1.0);
+    let from = center + 2.0 * Vec2::new((PI / 6.0).cos(), (PI / 6.0).sin());
+    let to = center + 2.0 * Vec2::new((PI / 3.0).cos(), (PI / 3.0).sin());
+    let arc = Arc::new_with_fixed_center(to, from, center, CircularDirection::CCW);
+    let bb = arc.get_bounding_box();
+    assert_eq!(bb.min, Vec2::new(to.x, from.y));
+    assert_eq!(bb.max, Vec2::new(from.x, to.y));
+
+    let half = Arc::new_with_fixed_center(Vec2::new(-1.0, 1.0), Vec2::new(3.0, 1.0), center, CircularDirection::CCW);
+    let bb = half.get_bounding_box();
+    assert!((bb.min - Vec2::new(-1.0, 1.0)).magnitude() < 1e-5);
+    assert!((bb.max - Vec2::new(3.0, 3.0)).magnitude() < 1e-5);
+  }
+}
@@ -2,4 +2,5 @@ mod serializable;
 mod svg_composer;
 mod bounding_box;
 pub use svg_composer::SvgComposer;
+pub(crate) use bounding_box::BoundingBoxTrait;
 
@@ -1,4 +1,4 @@
-use crate::plotter::{ Line, Arc, CircularDirection };
+use crate::plotter::{ Line, Arc, CircularDirection, DEFAULT_TOLERANCE };
 use super::bounding_box::BoundingBoxTrait;
 use std::f32::consts::PI;
 
@@ -25,21 +25,30 @@ impl Serializable for Arc {
   }
   fn serialize(&self) -> String {
     use CircularDirection::*;
-    let Arc{mut to, direction, center, angle_length, direction_in_end_point, ..} = self;
+    let Arc{from, to, direction, center, angle_length, ..} = self;
     let radius = (to - center).magnitude();
-    let is_large = *angle_length > PI;
-    if angle_length - 2.0 * PI <= f32::EPSILON {
-      to += -direction_in_end_point * 1e-7;
+    let sweep = match direction{ CCW => 1, _ => 0 };
+    if (from - to).magnitude() <= f32::EPSILON {
+      // svg cannot draw arc with coinciding ends, so full circle goes as curves
+      self.to_cubic_beziers(DEFAULT_TOLERANCE).iter()
+        .map(|curve| format!("C {} {} {} {} {} {}",
+          curve.from_control.x, curve.from_control.y,
+          curve.to_control.x, curve.to_control.y,
+          curve.to.x, curve.to.y
+        ))
+        .collect::<Vec<String>>()
+        .join(" ")
+    } else {
+      let is_large = *angle_length > PI;
+      format!("A {} {} {} {} {} {} {}", 
+              radius, 
+              radius, 
+              0, 
+              if is_large {1}else{0}, 
+              sweep, 
+              to.x, 
+              to.y
+              )
     }
-
-    format!("A {} {} {} {} {} {} {}", 
-            radius, 
-            radius, 
-            0, 
-            if is_large {1}else{0}, 
-            match direction{ CCW => 1, _ => 0 }, 
-            to.x, 
-            to.y
-            )
   }
 }
@@ -1,4 +1,4 @@
-use crate::plotter::{ Tree, Path, Region, AlgebraicPathElement };
+use crate::plotter::{ Region, AlgebraicPathElement };
 use crate::parser::{ Unit, Polarity };
 use super::bounding_box::*;
 use super::serializable::*;
@@ -27,61 +27,22 @@ pub struct SvgComposer {
 
 impl SvgComposer {
 
-  fn region_to_svg_paths(starting_polirity: Polarity, region: Tree<Path>) -> Vec<SvgPath> {
-    let mut result: Vec<SvgPath> = Vec::new();
-    let Path{ elements, ..} = &region.data;
-    result.push(
-      SvgPath {
-        polarity: starting_polirity.clone(),
-        elements: elements.iter()
+  fn region_to_svg_paths(region: &Region) -> Vec<SvgPath> {
+    region.walk().into_iter()
+      .map(|contour| SvgPath {
+        polarity: contour.polarity,
+        elements: contour.path.elements.iter()
           .map(|item| SvgElement::new(item.algebraic()))
           .collect()
-      });
-
-    let others = region.into_iter().map(|child| Self::region_to_svg_paths(starting_polirity.switch(), *child)).flatten().collect::<Vec<_>>();
-    result.extend(others);
-    result
-      
-
-    /*
-    for child in region.into_iter() {
-      result.push(
-        SvgPath {
-          polarity: starting_polirity.clone(),
-          elements: elements.iter()
-            .map(|item| SvgElement::new(item.algebraic()))
-            .collect()
-        });
-
-      let mut new_paths = Self::region_to_svg_paths(starting_polirity.switch(), *child);
-      result.append(&mut new_paths);
-    }
-    result
-
-    */
-
-    // let mut polarity = region.starting_polirity;
-
-
-    
-    /*
-    region.paths.into_iter().map(|path| {
-      let svg_path: Vec<SvgElement> = path.elements.into_iter().map(|item| {
-        SvgElement::new(item.algebraic())
-      }).collect();
-
-      SvgPath {
-        polarity: polarity.switch(),
-        elements: svg_path
-      }
-    }).collect()
-    */
+      })
+      .collect()
   }
+
   pub fn new (regions: Vec<Region>, unit: Unit) -> Self {
     println!("REGs: {}", regions.len());
     let paths = regions.into_iter()
       .map(|r|  {
-        Self::region_to_svg_paths(r.starting_polirity, r.paths)
+        Self::region_to_svg_paths(&r)
       })
       .flatten()
       .collect::<Vec<SvgPath>>();